@@ -1,11 +1,11 @@
 use itertools::Itertools;
-use std::cmp::Ordering::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 type Rule = (u32, u32);
+type Graph = HashMap<u32, HashSet<u32>>;
 
 pub struct Input {
-    rules: Vec<Rule>,
+    rules: Graph,
     updates: Vec<Vec<u32>>,
 }
 
@@ -17,7 +17,10 @@ pub fn parse(input: &str) -> Input {
         .lines()
         .filter_map(|line| line.split('|').collect_tuple())
         .map(|(a, b)| (a.parse().unwrap(), b.parse().unwrap()))
-        .collect();
+        .fold(Graph::new(), |mut graph, (before, after)| {
+            graph.entry(before).or_default().insert(after);
+            graph
+        });
 
     let updates = updates
         .lines()
@@ -27,33 +30,92 @@ pub fn parse(input: &str) -> Input {
     Input { rules, updates }
 }
 
-fn is_valid(sequence: &[u32], rules: &[Rule]) -> bool {
-    sequence
+// only the rules where both pages are part of the update
+fn update_graph(update: &[u32], rules: &Graph) -> Graph {
+    let pages: HashSet<_> = update.iter().copied().collect();
+    update
         .iter()
-        .tuple_windows()
-        .all(|(&a, &b)| !rules.iter().any(|&(x, y)| a == y && b == x))
+        .map(|page| {
+            let after = rules.get(page).into_iter().flatten();
+            (
+                *page,
+                after.filter(|p| pages.contains(p)).copied().collect(),
+            )
+        })
+        .collect()
 }
 
-fn sort(sequence: &[u32], rules: &[Rule]) -> Vec<u32> {
-    let nums: HashSet<_> = sequence.iter().copied().collect();
-    sequence
+fn violations(update: &[u32], rules: &Graph) -> Vec<Rule> {
+    let positions: HashMap<_, _> = update.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+    update_graph(update, rules)
         .iter()
-        .copied()
-        .sorted_by(|&a, &b| {
-            rules
-                .iter()
-                .filter(|&&(x, y)| nums.contains(&x) && nums.contains(&y))
-                .find(|&&(x, y)| (a, b) == (y, x) || (a, b) == (x, y))
-                .map_or(Equal, |&(x, _)| if x == a { Less } else { Greater })
-        })
+        .flat_map(|(&before, after)| after.iter().map(move |&after| (before, after)))
+        .filter(|(before, after)| positions[before] > positions[after])
+        .sorted()
         .collect()
 }
 
+// kahn's algorithm, keeping the update's own order between pages with no rule between them;
+// fails with one of the cycles when the rules contradict each other
+fn topological_order(update: &[u32], rules: &Graph) -> Result<Vec<u32>, Vec<u32>> {
+    let graph = update_graph(update, rules);
+    let mut incoming: HashMap<u32, usize> = update.iter().map(|&p| (p, 0)).collect();
+    graph
+        .values()
+        .flatten()
+        .for_each(|p| *incoming.get_mut(p).unwrap() += 1);
+
+    let mut ready: VecDeque<_> = update
+        .iter()
+        .filter(|p| incoming[p] == 0)
+        .copied()
+        .collect();
+    let mut order = vec![];
+
+    while let Some(page) = ready.pop_front() {
+        order.push(page);
+        for next in update.iter().filter(|p| graph[&page].contains(p)) {
+            let count = incoming.get_mut(next).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push_back(*next);
+            }
+        }
+    }
+
+    if order.len() == update.len() {
+        return Ok(order);
+    }
+
+    // every leftover page still has a leftover page before it, so walking backwards must repeat
+    let before = |page: u32| {
+        update
+            .iter()
+            .copied()
+            .find(|p| incoming[p] > 0 && graph[p].contains(&page))
+            .unwrap()
+    };
+    let start = update.iter().copied().find(|p| incoming[p] > 0).unwrap();
+    let walk = std::iter::successors(Some(start), |&p| Some(before(p))).take(update.len() + 1);
+    let mut seen = vec![];
+    for page in walk {
+        if let Some(i) = seen.iter().position(|&p| p == page) {
+            let mut cycle = seen.split_off(i);
+            cycle.reverse();
+            let first = cycle.iter().position_min().unwrap();
+            cycle.rotate_left(first);
+            return Err(cycle);
+        }
+        seen.push(page);
+    }
+    unreachable!()
+}
+
 pub fn part1(input: &Input) -> String {
     input
         .updates
         .iter()
-        .filter(|seq| is_valid(seq, &input.rules))
+        .filter(|seq| violations(seq, &input.rules).is_empty())
         .map(|seq| seq[seq.len() / 2])
         .sum::<u32>()
         .to_string()
@@ -63,12 +125,43 @@ pub fn part2(input: &Input) -> String {
     input
         .updates
         .iter()
-        .filter(|seq| !is_valid(seq, &input.rules))
-        .map(|seq| sort(seq, &input.rules)[seq.len() / 2])
+        .filter(|seq| !violations(seq, &input.rules).is_empty())
+        // an update whose rules go round in a cycle has no fixed order, so it's left out,
+        // `debug check` shows the cycle
+        .filter_map(|seq| topological_order(seq, &input.rules).ok())
+        .map(|order| order[order.len() / 2])
         .sum::<u32>()
         .to_string()
 }
 
+pub fn debug(input: &Input, args: &[String]) -> String {
+    match args[0].as_str() {
+        "check" => input
+            .updates
+            .iter()
+            .map(|seq| {
+                let update = seq.iter().join(",");
+                let broken = violations(seq, &input.rules);
+                match (broken.is_empty(), topological_order(seq, &input.rules)) {
+                    (true, _) => format!("{update}: valid"),
+                    (false, Ok(order)) => format!(
+                        "{update}: breaks {}, fixed as {}",
+                        broken.iter().map(|(a, b)| format!("{a}|{b}")).join(" "),
+                        order.iter().join(",")
+                    ),
+                    (false, Err(cycle)) => format!(
+                        "{update}: breaks {}, but the rules cycle {} -> {}",
+                        broken.iter().map(|(a, b)| format!("{a}|{b}")).join(" "),
+                        cycle.iter().join(" -> "),
+                        cycle[0]
+                    ),
+                }
+            })
+            .join("\n"),
+        mode => format!("unknown mode {mode}, expected check"),
+    }
+}
+
 #[test]
 fn test_day05() {
     let input = parse(
@@ -103,4 +196,20 @@ fn test_day05() {
     );
     assert_eq!(part1(&input), "143");
     assert_eq!(part2(&input), "123");
+
+    assert_eq!(violations(&input.updates[5], &input.rules).len(), 4);
+    assert_eq!(
+        topological_order(&input.updates[5], &input.rules),
+        Ok(vec![97, 75, 47, 29, 13])
+    );
+
+    let input = parse("1|2\n2|3\n3|1\n\n1,2,3");
+    assert_eq!(
+        topological_order(&input.updates[0], &input.rules),
+        Err(vec![1, 2, 3])
+    );
+
+    // the cyclic update is skipped, the other one still counts
+    let input = parse("1|2\n2|3\n3|1\n5|4\n\n1,2,3\n4,5");
+    assert_eq!(part2(&input), "4");
 }
@@ -1,3 +1,4 @@
+use itertools::Itertools;
 use rayon::prelude::*;
 use std::collections::HashSet;
 
@@ -89,7 +90,7 @@ fn next_state(grid: &Grid, guard: Guard) -> Option<Guard> {
         })
 }
 
-fn walk_path(grid: &Grid, start: Guard, obstruction: Option<Point>) -> HashSet<Point> {
+fn trace(grid: &Grid, start: Guard, obstruction: Option<Point>) -> Vec<Guard> {
     let mut seen = HashSet::new();
     seen.insert(start);
 
@@ -102,48 +103,127 @@ fn walk_path(grid: &Grid, start: Guard, obstruction: Option<Point>) -> HashSet<P
             },
         })
     })
-    .map(|guard| guard.pos)
     .collect()
 }
 
-fn adjacent_positions(path: &HashSet<Point>, grid: &Grid) -> Vec<Point> {
-    let (max_x, max_y) = (grid[0].len() as i32, grid.len() as i32);
-    let directions = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+fn walk_path(grid: &Grid, start: Guard, obstruction: Option<Point>) -> HashSet<Point> {
+    trace(grid, start, obstruction)
+        .into_iter()
+        .map(|guard| guard.pos)
+        .collect()
+}
 
-    path.iter()
-        .flat_map(|&(x, y)| {
-            directions.iter().filter_map(move |&(dx, dy)| {
-                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
-                match nx >= 0 && nx < max_x && ny >= 0 && ny < max_y {
-                    true => Some((nx as usize, ny as usize)),
-                    false => None,
+// for every cell and direction, where the guard stops in front of the next obstacle
+struct JumpTable {
+    width: usize,
+    stops: Vec<Option<Point>>,
+}
+
+impl JumpTable {
+    fn new(grid: &Grid) -> Self {
+        let (width, height) = (grid[0].len(), grid.len());
+        let mut stops = vec![None; width * height * 4];
+        let mut sweep = |direction: Direction, cells: &mut dyn Iterator<Item = Point>| {
+            cells.fold(None, |stop, (x, y)| match grid[y][x] {
+                '#' => direction
+                    .rotate()
+                    .rotate()
+                    .step(x, y, width - 1, height - 1),
+                _ => {
+                    stops[(y * width + x) * 4 + direction as usize] = stop;
+                    stop
                 }
+            });
+        };
+
+        for x in 0..width {
+            sweep(Direction::Up, &mut (0..height).map(|y| (x, y)));
+            sweep(Direction::Down, &mut (0..height).rev().map(|y| (x, y)));
+        }
+        for y in 0..height {
+            sweep(Direction::Left, &mut (0..width).map(|x| (x, y)));
+            sweep(Direction::Right, &mut (0..width).rev().map(|x| (x, y)));
+        }
+
+        JumpTable { width, stops }
+    }
+
+    fn stop(&self, guard: Guard, obstacle: Point) -> Option<Point> {
+        let ((x, y), (ox, oy)) = (guard.pos, obstacle);
+        let blocked = match guard.direction {
+            Direction::Up if ox == x && oy < y => Some((x, oy + 1)),
+            Direction::Down if ox == x && oy > y => Some((x, oy - 1)),
+            Direction::Left if oy == y && ox < x => Some((ox + 1, y)),
+            Direction::Right if oy == y && ox > x => Some((ox - 1, y)),
+            _ => None,
+        };
+        let table = self.stops[(y * self.width + x) * 4 + guard.direction as usize];
+        let dist = |(px, py): Point| px.abs_diff(x) + py.abs_diff(y);
+
+        match (table, blocked) {
+            (Some(a), Some(b)) => Some(if dist(a) < dist(b) { a } else { b }),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // only visits the turning points, so a check costs as much as the number of turns
+    fn loops(&self, start: Guard, obstacle: Point) -> bool {
+        let mut seen = HashSet::new();
+
+        std::iter::successors(Some(start), |&guard| {
+            self.stop(guard, obstacle).map(|pos| Guard {
+                pos,
+                direction: guard.direction.rotate(),
             })
         })
-        .collect::<HashSet<_>>()
+        .any(|guard| !seen.insert(guard))
+    }
+}
+
+fn loop_obstacles(grid: &Grid, guard: Guard) -> Vec<Point> {
+    let jumps = JumpTable::new(grid);
+
+    walk_path(grid, guard, None)
         .into_iter()
+        .filter(|&pos| pos != guard.pos)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter(|&pos| jumps.loops(guard, pos))
         .collect()
 }
 
-fn detect_loop(grid: &Grid, guard: Guard, obstacle: Point, max_steps: usize) -> bool {
-    let edge = |p: Point| p.0 == 0 || p.0 == grid[0].len() - 1 || p.1 == 0 || p.1 == grid.len() - 1;
-    let mut visited = HashSet::new();
+fn render(grid: &Grid, guard: Guard, obstruction: Option<Point>, marked: &[Point]) -> String {
+    let mut canvas = grid.clone();
+    let path = trace(grid, guard, obstruction);
 
-    (0..max_steps)
-        .try_fold(guard, |current, _| {
-            if !visited.insert(current) {
-                return Err(true);
-            }
+    path.iter().for_each(|g| {
+        let (x, y) = g.pos;
+        let line = match g.direction {
+            Direction::Up | Direction::Down => '|',
+            Direction::Left | Direction::Right => '-',
+        };
+        canvas[y][x] = match canvas[y][x] {
+            '^' => '^',
+            '.' => line,
+            c if c == line => c,
+            _ => '+',
+        };
+    });
 
-            match next_state(grid, current) {
-                Some(next) if next.pos == obstacle => Ok(current.rotate()),
-                Some(next) if edge(next.pos) => Err(false),
-                Some(next) => Ok(next),
-                None => Err(false),
-            }
-        })
-        .err()
-        == Some(true)
+    path.iter()
+        .tuple_windows()
+        .filter(|(a, b)| a.pos == b.pos && grid[a.pos.1][a.pos.0] != '^')
+        .for_each(|(a, _)| canvas[a.pos.1][a.pos.0] = '+');
+
+    marked
+        .iter()
+        .chain(&obstruction)
+        .for_each(|&(x, y)| canvas[y][x] = 'O');
+
+    canvas
+        .iter()
+        .map(|row| row.iter().collect::<String>())
+        .join("\n")
 }
 
 pub fn part1(input: &(Grid, Guard)) -> String {
@@ -153,18 +233,19 @@ pub fn part1(input: &(Grid, Guard)) -> String {
 
 pub fn part2(input: &(Grid, Guard)) -> String {
     let (grid, guard) = input;
+    loop_obstacles(grid, *guard).len().to_string()
+}
 
-    let initial_path = walk_path(grid, *guard, None);
-    let max_steps = grid.len() * grid[0].len() * 4;
+pub fn debug(input: &(Grid, Guard), args: &[String]) -> String {
+    let (grid, guard) = input;
+    let coord = |i: usize| args[i].parse().expect("Coordinates must be numbers");
 
-    adjacent_positions(&initial_path, grid)
-        .into_iter()
-        .filter(|&pos| pos != guard.pos)
-        .collect::<Vec<_>>()
-        .into_par_iter()
-        .filter(|&pos| detect_loop(grid, *guard, pos, max_steps))
-        .count()
-        .to_string()
+    match args[0].as_str() {
+        "path" => render(grid, *guard, None, &loop_obstacles(grid, *guard)),
+        "loop" if args.len() < 3 => "usage: loop <x> <y>".to_string(),
+        "loop" => render(grid, *guard, Some((coord(1), coord(2))), &[]),
+        mode => format!("unknown mode {mode}, expected path or loop"),
+    }
 }
 
 #[test]
@@ -183,4 +264,25 @@ fn test_day06() {
     );
     assert_eq!(part1(&input), "41");
     assert_eq!(part2(&input), "6");
+
+    let (grid, guard) = &input;
+    assert_eq!(
+        render(grid, *guard, Some((3, 6)), &[]),
+        "....#.....
+         ....+---+#
+         ....|...|.
+         ..#.|...|.
+         ....|..#|.
+         ....|...|.
+         .#.O^---+.
+         ........#.
+         #.........
+         ......#..."
+            .lines()
+            .map(str::trim)
+            .join("\n")
+    );
+
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect_vec();
+    assert_eq!(debug(&input, &args(&["loop", "3"])), "usage: loop <x> <y>");
 }
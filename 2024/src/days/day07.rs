@@ -5,25 +5,100 @@ pub struct Equation {
     nums: Vec<i64>,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum Op {
     Add,
     Multiply,
     Concat,
+    Subtract,
+    Xor,
 }
 
-fn evaluate(numbers: &[i64], operators: &Vec<&Op>) -> i64 {
-    std::iter::zip(operators, &numbers[1..]).fold(numbers[0], |acc, (op, &num)| match op {
-        Op::Add => acc + num,
-        Op::Multiply => acc * num,
-        Op::Concat => format!("{acc}{num}").parse().unwrap(),
-    })
+// the power of ten just above num, so that acc || num = acc * shift + num
+fn shift(num: i64) -> i64 {
+    10i64.pow(num.max(1).ilog10() + 1)
 }
 
-fn combinations(equation: &Equation, operators: &[Op]) -> bool {
-    (0..equation.nums.len() - 1)
-        .map(|_| operators)
-        .multi_cartesian_product()
-        .any(|ops| evaluate(&equation.nums, &ops) == equation.value)
+impl Op {
+    fn from_symbol(symbol: char) -> Option<Op> {
+        match symbol {
+            '+' => Some(Op::Add),
+            '*' => Some(Op::Multiply),
+            '|' => Some(Op::Concat),
+            '-' => Some(Op::Subtract),
+            '^' => Some(Op::Xor),
+            _ => None,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Multiply => "*",
+            Op::Concat => "||",
+            Op::Subtract => "-",
+            Op::Xor => "^",
+        }
+    }
+
+    fn apply(&self, acc: i64, num: i64) -> i64 {
+        match self {
+            Op::Add => acc + num,
+            Op::Multiply => acc * num,
+            Op::Concat => acc * shift(num) + num,
+            Op::Subtract => acc - num,
+            Op::Xor => acc ^ num,
+        }
+    }
+
+    // the value before this op was applied with num, if any value could have produced target
+    fn undo(&self, target: i64, num: i64) -> Option<i64> {
+        match self {
+            Op::Add => target.checked_sub(num),
+            Op::Multiply => (num != 0 && target % num == 0).then(|| target / num),
+            Op::Concat => (target >= num && (target - num) % shift(num) == 0)
+                .then(|| (target - num) / shift(num)),
+            Op::Subtract => target.checked_add(num),
+            Op::Xor => Some(target ^ num),
+        }
+    }
+
+    // with non-negative inputs, these never make the running value smaller
+    fn growing(&self) -> bool {
+        matches!(self, Op::Add | Op::Multiply | Op::Concat)
+    }
+}
+
+fn evaluate(numbers: &[i64], operators: &[Op]) -> i64 {
+    std::iter::zip(operators, &numbers[1..]).fold(numbers[0], |acc, (op, &num)| op.apply(acc, num))
+}
+
+// works from the target back to the first number, undoing one operator at a time
+fn solve(nums: &[i64], target: i64, operators: &[Op], growing: bool) -> Option<Vec<Op>> {
+    match nums {
+        [first] => (*first == target).then(Vec::new),
+        [rest @ .., last] => operators.iter().find_map(|op| {
+            // anything times zero is zero, so whatever the prefix evaluates to will do
+            if *op == Op::Multiply && *last == 0 && target == 0 {
+                let mut ops = vec![operators[0]; rest.len() - 1];
+                ops.push(*op);
+                return Some(ops);
+            }
+
+            let prev = op
+                .undo(target, *last)
+                .filter(|&prev| !growing || prev >= 0)?;
+            let mut ops = solve(rest, prev, operators, growing)?;
+            ops.push(*op);
+            Some(ops)
+        }),
+        [] => None,
+    }
+}
+
+fn assignment(equation: &Equation, operators: &[Op]) -> Option<Vec<Op>> {
+    let growing = operators.iter().all(Op::growing) && equation.nums.iter().all(|&n| n >= 0);
+    solve(&equation.nums, equation.value, operators, growing)
 }
 
 pub fn parse(input: &str) -> Vec<Equation> {
@@ -42,22 +117,39 @@ pub fn parse(input: &str) -> Vec<Equation> {
         .collect()
 }
 
-pub fn part1(input: &Vec<Equation>) -> String {
+fn calibration(input: &[Equation], operators: &[Op]) -> String {
     input
         .iter()
-        .filter(|eq| combinations(eq, &[Op::Add, Op::Multiply]))
+        .filter(|eq| assignment(eq, operators).is_some())
         .map(|eq| eq.value)
         .sum::<i64>()
         .to_string()
 }
 
-pub fn part2(input: &Vec<Equation>) -> String {
+pub fn part1(input: &[Equation]) -> String {
+    calibration(input, &[Op::Add, Op::Multiply])
+}
+
+pub fn part2(input: &[Equation]) -> String {
+    calibration(input, &[Op::Add, Op::Multiply, Op::Concat])
+}
+
+pub fn debug(input: &[Equation], args: &[String]) -> String {
+    let operators = args[0]
+        .chars()
+        .map(|symbol| Op::from_symbol(symbol).expect("Operators must be one of +*|-^"))
+        .collect_vec();
+
     input
         .iter()
-        .filter(|eq| combinations(eq, &[Op::Add, Op::Multiply, Op::Concat]))
-        .map(|eq| eq.value)
-        .sum::<i64>()
-        .to_string()
+        .map(|eq| match assignment(eq, &operators) {
+            Some(ops) => ops.iter().zip(&eq.nums[1..]).fold(
+                format!("{} = {}", evaluate(&eq.nums, &ops), eq.nums[0]),
+                |line, (op, num)| format!("{line} {} {num}", op.symbol()),
+            ),
+            None => format!("{} can't be made from {:?}", eq.value, eq.nums),
+        })
+        .join("\n")
 }
 
 #[test]
@@ -75,4 +167,25 @@ fn test_day07() {
     );
     assert_eq!(part1(&input), "3749");
     assert_eq!(part2(&input), "11387");
+
+    let all = [Op::Add, Op::Multiply, Op::Concat];
+    let ops = assignment(&input[4], &all).unwrap();
+    assert_eq!(ops, [Op::Multiply, Op::Concat, Op::Multiply]);
+    assert_eq!(evaluate(&input[4].nums, &ops), 7290);
+
+    let input = parse("4: 10 3 3\n9: 12 5");
+    assert_eq!(
+        assignment(&input[0], &[Op::Add, Op::Subtract]),
+        Some(vec![Op::Subtract, Op::Subtract])
+    );
+    assert_eq!(assignment(&input[1], &[Op::Xor]), Some(vec![Op::Xor]));
+
+    let input = parse("7: 5 0 7\n0: 3 4 0\n5: 5 0");
+    let ops = assignment(&input[0], &[Op::Add, Op::Multiply]).unwrap();
+    assert_eq!(evaluate(&input[0].nums, &ops), 7);
+    assert_eq!(
+        assignment(&input[1], &[Op::Add, Op::Multiply]),
+        Some(vec![Op::Add, Op::Multiply])
+    );
+    assert_eq!(assignment(&input[2], &[Op::Multiply]), None);
 }
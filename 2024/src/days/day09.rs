@@ -1,121 +1,160 @@
-pub fn parse(input: &str) -> Vec<i32> {
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+#[derive(Clone, Copy)]
+struct File {
+    id: usize,
+    start: usize,
+    len: usize,
+}
+
+pub struct Disk {
+    files: Vec<File>,
+    free: Vec<(usize, usize)>,
+    size: usize,
+}
+
+pub fn parse(input: &str) -> Disk {
+    let mut disk = Disk {
+        files: vec![],
+        free: vec![],
+        size: 0,
+    };
+
     input
+        .trim()
         .chars()
-        .map(|c| c.to_digit(10).unwrap())
+        .map(|c| c.to_digit(10).unwrap() as usize)
         .enumerate()
-        .fold(Vec::new(), |mut acc, (i, len)| {
-            acc.extend(
-                std::iter::repeat(if i % 2 == 0 { i as i32 / 2 } else { -1 }).take(len as usize),
-            );
-            acc
-        })
+        .for_each(|(i, len)| {
+            match i % 2 {
+                0 => disk.files.push(File {
+                    id: i / 2,
+                    start: disk.size,
+                    len,
+                }),
+                _ if len > 0 => disk.free.push((disk.size, len)),
+                _ => (),
+            }
+            disk.size += len;
+        });
+
+    disk
 }
 
-fn compact(blocks: &[i32]) -> Vec<i32> {
-    let mut result = blocks.to_vec();
+// fills each gap from the left with blocks taken off the end of the rightmost file
+fn compact(disk: &Disk) -> Vec<File> {
+    let mut remaining = disk.files.clone();
+    let mut moved = vec![];
 
-    result
-        .iter()
-        .enumerate()
-        .filter(|(_, &x)| x == -1)
-        .map(|(i, _)| i)
-        .collect::<Vec<_>>()
-        .iter()
-        .for_each(|&gap_pos| {
-            if let Some(file_pos) = result[gap_pos..]
-                .iter()
-                .enumerate()
-                .filter(|(_, &id)| id != -1)
-                .map(|(i, _)| i + gap_pos)
-                .last()
-            {
-                result.swap(gap_pos, file_pos);
+    for &(mut start, mut len) in &disk.free {
+        while let Some(last) = remaining
+            .last_mut()
+            .filter(|last| len > 0 && last.start > start)
+        {
+            let taken = len.min(last.len);
+            moved.push(File {
+                id: last.id,
+                start,
+                len: taken,
+            });
+
+            last.len -= taken;
+            if last.len == 0 {
+                remaining.pop();
             }
-        });
+            start += taken;
+            len -= taken;
+        }
+    }
 
-    result
+    remaining.into_iter().chain(moved).collect()
 }
 
-fn compact_whole(blocks: &[i32]) -> Vec<i32> {
-    let max_file_id = blocks
+// keeps a min-heap of gap starts for every gap size, so each file only checks one gap per size
+fn compact_whole(disk: &Disk) -> Vec<File> {
+    let max_gap = disk.free.iter().map(|&(_, len)| len).max().unwrap_or(0);
+    let mut gaps = vec![BinaryHeap::new(); max_gap + 1];
+    disk.free
         .iter()
-        .filter(|&&x| x >= 0)
-        .max()
-        .copied()
-        .unwrap_or(0);
+        .for_each(|&(start, len)| gaps[len].push(Reverse(start)));
 
-    (0..=max_file_id)
+    disk.files
+        .iter()
         .rev()
-        .fold(blocks.to_vec(), |acc, file_id| {
-            let file_positions: Vec<usize> = acc
-                .iter()
-                .enumerate()
-                .filter(|(_, &id)| id == file_id)
-                .map(|(i, _)| i)
-                .collect();
-
-            if file_positions.is_empty() {
-                return acc;
-            }
+        .map(|&file| {
+            let gap = (file.len..=max_gap)
+                .filter_map(|len| gaps[len].peek().map(|&Reverse(start)| (start, len)))
+                .filter(|&(start, _)| start < file.start)
+                .min();
 
-            let file_size = file_positions.len();
-            let mut result = acc;
-
-            let gaps = result
-                .iter()
-                .enumerate()
-                .scan((None, 0), |(start, size), (i, &block)| {
-                    match block {
-                        -1 => {
-                            if start.is_none() {
-                                *start = Some(i);
-                            }
-                            *size += 1;
-                        }
-                        _ => {
-                            *start = None;
-                            *size = 0;
-                        }
-                    }
-                    Some((*start, *size))
-                })
-                .filter(|&(start, size)| size >= file_size && start.unwrap() < file_positions[0])
-                .next();
-
-            if let Some((Some(gap_start), _)) = gaps {
-                file_positions
-                    .iter()
-                    .enumerate()
-                    .for_each(|(offset, &src_pos)| {
-                        result[gap_start + offset] = file_id;
-                        result[src_pos] = -1;
-                    });
+            match gap {
+                Some((start, len)) => {
+                    gaps[len].pop();
+                    gaps[len - file.len].push(Reverse(start + file.len));
+                    File { start, ..file }
+                }
+                None => file,
             }
-
-            result
         })
+        .collect()
 }
 
-fn checksum(blocks: &[i32]) -> i64 {
-    blocks
+fn checksum(files: &[File]) -> usize {
+    files
         .iter()
-        .enumerate()
-        .filter(|(_, &id)| id != -1)
-        .map(|(pos, &id)| pos as i64 * id as i64)
+        .map(|f| f.id * (f.start * f.len + f.len * f.len.saturating_sub(1) / 2))
         .sum()
 }
 
-pub fn part1(input: &[i32]) -> String {
+// the puzzle's layout, with ids past 9 shown as '#'
+fn render(files: &[File], size: usize) -> String {
+    let mut blocks = vec!['.'; size];
+    files.iter().for_each(|f| {
+        let id = char::from_digit(f.id as u32, 10).filter(|_| f.id < 10);
+        blocks[f.start..f.start + f.len].fill(id.unwrap_or('#'));
+    });
+    blocks.into_iter().collect()
+}
+
+pub fn part1(input: &Disk) -> String {
     checksum(&compact(input)).to_string()
 }
 
-pub fn part2(input: &[i32]) -> String {
+pub fn part2(input: &Disk) -> String {
     checksum(&compact_whole(input)).to_string()
 }
 
+pub fn debug(input: &Disk, args: &[String]) -> String {
+    let compacted = match args[0].as_str() {
+        "blocks" => compact(input),
+        "files" => compact_whole(input),
+        mode => return format!("unknown mode {mode}, expected blocks or files"),
+    };
+
+    [&input.files, &compacted]
+        .iter()
+        .map(|files| render(files, input.size))
+        .join("\n")
+}
+
 #[test]
 fn test_day09() {
     let input = parse("2333133121414131402");
     assert_eq!(part1(&input), "1928");
     assert_eq!(part2(&input), "2858");
+
+    assert_eq!(
+        render(&input.files, input.size),
+        "00...111...2...333.44.5555.6666.777.888899"
+    );
+    assert_eq!(
+        render(&compact(&input), input.size),
+        "0099811188827773336446555566.............."
+    );
+    assert_eq!(
+        render(&compact_whole(&input), input.size),
+        "00992111777.44.333....5555.6666.....8888.."
+    );
 }
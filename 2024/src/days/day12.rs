@@ -1,9 +1,7 @@
 use itertools::Itertools;
-use std::collections::{BTreeSet, HashSet};
 
 type Grid = Vec<Vec<char>>;
 type Point = (i32, i32);
-type Item = (Point, usize);
 
 const DX: [i32; 4] = [-1, 0, 1, 0];
 const DY: [i32; 4] = [0, 1, 0, -1];
@@ -15,147 +13,198 @@ pub fn parse(input: &str) -> Grid {
         .collect()
 }
 
-fn find_region(grid: &Grid, start: Point, visited: &mut HashSet<Point>) -> (usize, usize) {
-    let (rows, cols) = (grid.len() as i32, grid[0].len() as i32);
-    let target = grid[start.0 as usize][start.1 as usize];
-    let mut stack = vec![start];
-    let mut area = 1;
-    let mut perimeter = 0;
-    visited.insert(start);
-
-    while let Some((r, c)) = stack.pop() {
-        let neighbors: Vec<_> = DX
-            .iter()
-            .zip(DY.iter())
-            .map(|(&dx, &dy)| (r + dx, c + dy))
-            .collect();
-
-        perimeter += neighbors
-            .iter()
-            .filter(|&&(nr, nc)| {
-                !(0..rows).contains(&nr)
-                    || !(0..cols).contains(&nc)
-                    || grid[nr as usize][nc as usize] != target
-            })
-            .count();
-
-        let valid_neighbors: Vec<_> = neighbors
-            .into_iter()
-            .filter(|&(nr, nc)| {
-                (0..rows).contains(&nr)
-                    && (0..cols).contains(&nc)
-                    && grid[nr as usize][nc as usize] == target
-                    && !visited.contains(&(nr, nc))
-            })
-            .collect();
+struct Region {
+    plant: char,
+    cells: Vec<Point>,
+    perimeter: usize,
+    sides: usize,
+    bounds: (Point, Point),
+    holes: usize,
+}
+
+fn plant_at(grid: &Grid, (r, c): Point) -> Option<char> {
+    grid.get(usize::try_from(r).ok()?)?
+        .get(usize::try_from(c).ok()?)
+        .copied()
+}
+
+// a single flood fill per region, labelling every cell with the index of its region
+fn regions(grid: &Grid) -> (Vec<Region>, Vec<Vec<usize>>) {
+    let mut labels = vec![vec![usize::MAX; grid[0].len()]; grid.len()];
+    let mut regions = vec![];
 
-        for pos in valid_neighbors {
-            visited.insert(pos);
-            stack.push(pos);
-            area += 1;
+    for (r, c) in (0..grid.len()).cartesian_product(0..grid[0].len()) {
+        if labels[r][c] != usize::MAX {
+            continue;
         }
+
+        let plant = grid[r][c];
+        let mut cells = vec![];
+        let mut stack = vec![(r as i32, c as i32)];
+        labels[r][c] = regions.len();
+
+        while let Some(pos) = stack.pop() {
+            cells.push(pos);
+            for d in 0..4 {
+                let next = (pos.0 + DX[d], pos.1 + DY[d]);
+                if plant_at(grid, next) == Some(plant) {
+                    let label = &mut labels[next.0 as usize][next.1 as usize];
+                    if *label == usize::MAX {
+                        *label = regions.len();
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        regions.push(describe(grid, &labels, plant, cells));
     }
 
-    (area, perimeter)
+    (regions, labels)
 }
 
-fn connected_segments(mut boundary: BTreeSet<Item>) -> usize {
-    let mut count = 0;
-    while !boundary.is_empty() {
-        count += 1;
-        let mut current = *boundary.iter().next().unwrap();
-        boundary.remove(&current);
-
-        while let Some(&next) = boundary.iter().find(|&&item| {
-            (0..4).any(|d| {
-                item.0 .0 == current.0 .0 + DX[d]
-                    && item.0 .1 == current.0 .1 + DY[d]
-                    && item.1 == current.1
+fn describe(grid: &Grid, labels: &[Vec<usize>], plant: char, cells: Vec<Point>) -> Region {
+    let id = labels[cells[0].0 as usize][cells[0].1 as usize];
+    let inside =
+        |(r, c): Point| plant_at(grid, (r, c)).is_some() && labels[r as usize][c as usize] == id;
+
+    let perimeter = cells
+        .iter()
+        .flat_map(|&(r, c)| (0..4).map(move |d| (r + DX[d], c + DY[d])))
+        .filter(|&next| !inside(next))
+        .count();
+
+    // a region has as many sides as corners, convex or concave
+    let sides = cells
+        .iter()
+        .flat_map(|&(r, c)| {
+            (0..4).map(move |d| {
+                let (a, b) = (
+                    (r + DX[d], c + DY[d]),
+                    (r + DX[(d + 1) % 4], c + DY[(d + 1) % 4]),
+                );
+                let diagonal = (r + DX[d] + DX[(d + 1) % 4], c + DY[d] + DY[(d + 1) % 4]);
+                (a, b, diagonal)
             })
-        }) {
-            boundary.remove(&next);
-            current = next;
-        }
-    }
+        })
+        .filter(|&(a, b, diagonal)| {
+            (!inside(a) && !inside(b)) || (inside(a) && inside(b) && !inside(diagonal))
+        })
+        .count();
 
-    count
-}
+    let (rows, cols) = cells.iter().copied().unzip::<_, _, Vec<_>, Vec<_>>();
+    let bounds = (
+        (*rows.iter().min().unwrap(), *cols.iter().min().unwrap()),
+        (*rows.iter().max().unwrap(), *cols.iter().max().unwrap()),
+    );
 
-fn component(input: &Grid, visited: &mut Vec<Vec<bool>>, start: (usize, usize)) -> Vec<Point> {
-    let (n, m) = (input.len() as i32, input[0].len() as i32);
-    let target = input[start.0][start.1];
-    let mut component = Vec::new();
-    let mut stack = vec![(start.0 as i32, start.1 as i32)];
-    visited[start.0][start.1] = true;
-
-    while let Some(pos) = stack.pop() {
-        component.push(pos);
-        for d in 0..4 {
-            let (nx, ny) = (pos.0 + DX[d], pos.1 + DY[d]);
-            if (0..n).contains(&nx)
-                && (0..m).contains(&ny)
-                && !visited[nx as usize][ny as usize]
-                && input[nx as usize][ny as usize] == target
-            {
-                visited[nx as usize][ny as usize] = true;
-                stack.push((nx, ny));
-            }
-        }
+    Region {
+        plant,
+        perimeter,
+        sides,
+        bounds,
+        holes: holes(bounds, inside),
+        cells,
     }
-    component
 }
 
-fn boundary(input: &Grid, component: &[Point], target: char) -> BTreeSet<Item> {
-    let (n, m) = (input.len() as i32, input[0].len() as i32);
-    component
-        .iter()
-        .flat_map(|&(i, j)| (0..4).map(move |d| ((i + DX[d], j + DY[d]), d)))
-        .filter(|&(pos, _)| {
-            !(0..n).contains(&pos.0)
-                || !(0..m).contains(&pos.1)
-                || input[pos.0 as usize][pos.1 as usize] != target
+// pockets of other plants that can't reach the edge of the bounding box, even diagonally
+fn holes(((top, left), (bottom, right)): (Point, Point), inside: impl Fn(Point) -> bool) -> usize {
+    let (height, width) = ((bottom - top + 1) as usize, (right - left + 1) as usize);
+    let mut seen = vec![vec![false; width]; height];
+
+    (0..height)
+        .cartesian_product(0..width)
+        .filter(|&(r, c)| {
+            if seen[r][c] || inside((top + r as i32, left + c as i32)) {
+                return false;
+            }
+
+            let mut stack = vec![(r as i32, c as i32)];
+            let mut enclosed = true;
+            seen[r][c] = true;
+
+            while let Some((r, c)) = stack.pop() {
+                for (dr, dc) in (-1..=1).cartesian_product(-1..=1) {
+                    let (nr, nc) = (r + dr, c + dc);
+                    if nr < 0 || nc < 0 || nr >= height as i32 || nc >= width as i32 {
+                        enclosed = false;
+                    } else if !seen[nr as usize][nc as usize] && !inside((top + nr, left + nc)) {
+                        seen[nr as usize][nc as usize] = true;
+                        stack.push((nr, nc));
+                    }
+                }
+            }
+            enclosed
         })
-        .collect()
+        .count()
 }
 
-fn component_value(input: &Grid, visited: &mut Vec<Vec<bool>>, i: usize, j: usize) -> usize {
-    if visited[i][j] {
-        return 0;
-    }
+fn render(grid: &Grid, labels: &[Vec<usize>], color: bool) -> String {
+    let letters = ('A'..='Z').chain('a'..='z').collect_vec();
 
-    let component = component(input, visited, (i, j));
-    let boundary = boundary(input, &component, input[i][j]);
+    labels
+        .iter()
+        .enumerate()
+        .map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(c, &id)| match color {
+                    true => format!("\x1b[{}m{}\x1b[0m", 41 + id % 6, grid[r][c]),
+                    false => letters[id % letters.len()].to_string(),
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
 
-    component.len() * connected_segments(boundary)
+fn to_json(regions: &[Region]) -> String {
+    let objects = regions.iter().enumerate().map(|(id, region)| {
+        let ((top, left), (bottom, right)) = region.bounds;
+        format!(
+            "  {{\"id\": {id}, \"plant\": \"{}\", \"area\": {}, \"perimeter\": {}, \"sides\": {}, \
+             \"bounds\": {{\"top\": {top}, \"left\": {left}, \"bottom\": {bottom}, \"right\": {right}}}, \
+             \"holes\": {}}}",
+            region.plant,
+            region.cells.len(),
+            region.perimeter,
+            region.sides,
+            region.holes
+        )
+    });
+
+    format!("[\n{}\n]", objects.collect_vec().join(",\n"))
 }
 
 pub fn part1(input: &Grid) -> String {
-    let mut visited = HashSet::new();
-
-    (0..input.len())
-        .cartesian_product(0..input[0].len())
-        .map(|(r, c)| match visited.contains(&(r as i32, c as i32)) {
-            true => 0,
-            false => {
-                let (area, perimeter) = find_region(input, (r as i32, c as i32), &mut visited);
-                area * perimeter
-            }
-        })
+    regions(input)
+        .0
+        .iter()
+        .map(|region| region.cells.len() * region.perimeter)
         .sum::<usize>()
         .to_string()
 }
 
 pub fn part2(input: &Grid) -> String {
-    let (n, m) = (input.len(), input[0].len());
-    let mut visited = vec![vec![false; m]; n];
-
-    (0..n)
-        .cartesian_product(0..m)
-        .map(|(i, j)| component_value(input, &mut visited, i, j))
+    regions(input)
+        .0
+        .iter()
+        .map(|region| region.cells.len() * region.sides)
         .sum::<usize>()
         .to_string()
 }
 
+pub fn debug(input: &Grid, args: &[String]) -> String {
+    let (regions, labels) = regions(input);
+
+    match args[0].as_str() {
+        "color" => render(input, &labels, true),
+        "letters" => render(input, &labels, false),
+        "json" => to_json(&regions),
+        mode => format!("unknown mode {mode}, expected color, letters or json"),
+    }
+}
+
 #[test]
 fn test_day12() {
     let input = parse(
@@ -166,4 +215,16 @@ fn test_day12() {
     );
     assert_eq!(part1(&input), "140");
     assert_eq!(part2(&input), "80");
+
+    let input = parse(
+        "OOOOO
+         OXOXO
+         OOOOO
+         OXOXO
+         OOOOO",
+    );
+    let (regions, _) = regions(&input);
+    assert_eq!(regions[0].holes, 4);
+    assert_eq!((regions[0].perimeter, regions[0].sides), (36, 20));
+    assert_eq!(regions[0].bounds, ((0, 0), (4, 4)));
 }
@@ -1,5 +1,6 @@
 use itertools::Itertools;
 use regex::Regex;
+use std::collections::HashSet;
 
 type Point = (i32, i32);
 pub type Robot = (Point, Point);
@@ -51,19 +52,85 @@ fn robots_in_quads(robots: &[Robot], width: i32, height: i32) -> (usize, usize,
         })
 }
 
-fn robot_density(robots: &[Robot]) -> f64 {
-    let (sum, count) = robots
+fn positions_at(robots: &[Robot], t: i32, width: i32, height: i32) -> Vec<Point> {
+    robots
         .iter()
-        .map(|(pos, _)| pos)
-        .tuple_combinations()
-        .map(|(a, b)| {
-            let dx = (a.0 - b.0).abs() as f64;
-            let dy = (a.1 - b.1).abs() as f64;
-            (dx * dx + dy * dy).sqrt()
+        .map(|&(pos, vel)| {
+            (
+                (pos.0 + vel.0 * t).rem_euclid(width),
+                (pos.1 + vel.1 * t).rem_euclid(height),
+            )
         })
-        .fold((0.0, 0), |(sum, count), dist| (sum + dist, count + 1));
+        .collect()
+}
+
+fn variance(values: impl Iterator<Item = i32> + Clone) -> f64 {
+    let (sum, count) = values
+        .clone()
+        .fold((0, 0), |(sum, count), v| (sum + v, count + 1));
+    let mean = sum as f64 / count as f64;
+    values.map(|v| (v as f64 - mean).powi(2)).sum::<f64>() / count as f64
+}
 
-    sum / count as f64 // assume count > 0
+// x and y repeat independently every width and height steps, so the tightest step on each axis
+// can be found separately and combined with the chinese remainder theorem
+fn picture_step(robots: &[Robot], width: i32, height: i32) -> i32 {
+    let tightest = |period: i32, axis: fn(&Point) -> i32| {
+        (0..period)
+            .map(|t| {
+                let positions = positions_at(robots, t, width, height);
+                (t, variance(positions.iter().map(axis)))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(t, _)| t)
+            .unwrap()
+    };
+
+    let (tx, ty) = (tightest(width, |p| p.0), tightest(height, |p| p.1));
+    (0..height)
+        .map(|k| tx + k * width)
+        .find(|t| t % height == ty)
+        .unwrap()
+}
+
+fn longest_run(positions: &[Point]) -> usize {
+    positions
+        .iter()
+        .copied()
+        .sorted_by_key(|&(x, y)| (y, x))
+        .dedup()
+        .tuple_windows()
+        .fold((1, 1), |(best, run), (a, b)| {
+            let run = match (b.1 == a.1, b.0 == a.0 + 1) {
+                (true, true) => run + 1,
+                _ => 1,
+            };
+            (best.max(run), run)
+        })
+        .0
+}
+
+fn render(positions: &[Point], width: i32, height: i32) -> String {
+    let robots: HashSet<_> = positions.iter().collect();
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| match robots.contains(&(x, y)) {
+                    true => '#',
+                    false => '.',
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
+fn render_pbm(positions: &[Point], width: i32, height: i32) -> String {
+    format!(
+        "P1\n{width} {height}\n{}",
+        render(positions, width, height)
+            .replace('#', "1")
+            .replace('.', "0")
+    )
 }
 
 pub fn part1(input: &[Robot]) -> String {
@@ -74,15 +141,30 @@ pub fn part1(input: &[Robot]) -> String {
 }
 
 pub fn part2(input: &[Robot]) -> String {
-    (0..20000)
-        .scan(input.to_vec(), |state, t| {
-            let density = robot_density(state);
-            *state = simulate(state, 101, 103);
-            Some((t, density))
-        })
-        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
-        .map(|(t, _)| t.to_string())
-        .unwrap()
+    picture_step(input, 101, 103).to_string()
+}
+
+pub fn debug(input: &[Robot], args: &[String]) -> String {
+    let step = |i: usize| {
+        args.get(i)
+            .map(|s| s.parse().expect("Step must be a number"))
+    };
+
+    match args[0].as_str() {
+        "frame" | "pbm" if args.len() < 2 => format!("usage: {} <step>", args[0]),
+        "frame" => render(&positions_at(input, step(1).unwrap(), 101, 103), 101, 103),
+        "pbm" => render_pbm(&positions_at(input, step(1).unwrap(), 101, 103), 101, 103),
+        "tree" => {
+            let t = step(1).unwrap_or_else(|| picture_step(input, 101, 103));
+            let positions = positions_at(input, t, 101, 103);
+            format!(
+                "step {t} (longest run: {})\n{}",
+                longest_run(&positions),
+                render(&positions, 101, 103)
+            )
+        }
+        mode => format!("unknown mode {mode}, expected frame, pbm or tree"),
+    }
 }
 
 #[test]
@@ -103,4 +185,17 @@ fn test_day14() {
     );
     assert_eq!(part1(&input), "21");
     assert_eq!(part2(&input), "5253");
+
+    // on a 5x7 floor these all meet at (2, 3) on step 12, which is 2 mod 5 and 5 mod 7
+    let meeting = parse("p=0,5 v=1,1\np=3,0 v=2,2\np=1,2 v=3,3\np=4,4 v=4,4");
+    assert_eq!(picture_step(&meeting, 5, 7), 12);
+    assert_eq!(positions_at(&meeting, 12, 5, 7), [(2, 3); 4]);
+
+    let positions = [(1, 0), (2, 0), (3, 0), (0, 1), (2, 1), (2, 1)];
+    assert_eq!(longest_run(&positions), 3);
+    assert_eq!(render(&positions, 4, 2), ".###\n#.#.");
+    assert_eq!(render_pbm(&positions, 4, 2), "P1\n4 2\n0111\n1010");
+
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect_vec();
+    assert_eq!(debug(&input, &args(&["frame"])), "usage: frame <step>");
 }
@@ -103,36 +103,115 @@ fn process(grid: &mut Grid, r: usize, c: usize, dr: i32, dc: i32) -> Option<(usi
     }
 }
 
-fn solve(input: &Puzzle, expand: bool) -> String {
-    let mut grid = match expand {
-        true => expand_grid(input.grid.clone()),
-        false => input.grid.clone(),
-    };
-
-    let (r, c) = start(&grid);
-    grid[r][c] = '.';
-
-    input
-        .instructions
-        .chars()
-        .filter_map(|inst| match inst {
-            '^' => Some((-1, 0)),
-            '>' => Some((0, 1)),
-            'v' => Some((1, 0)),
-            '<' => Some((0, -1)),
+struct Move {
+    step: usize,
+    instruction: char,
+    blocked: bool,
+}
+
+struct Replay<'a> {
+    grid: Grid,
+    robot: (usize, usize),
+    instructions: std::str::Chars<'a>,
+    step: usize,
+}
+
+impl<'a> Replay<'a> {
+    fn new(input: &'a Puzzle, expand: bool) -> Self {
+        let mut grid = match expand {
+            true => expand_grid(input.grid.clone()),
+            false => input.grid.clone(),
+        };
+
+        let robot = start(&grid);
+        grid[robot.0][robot.1] = '.';
+
+        Replay {
+            grid,
+            robot,
+            instructions: input.instructions.chars(),
+            step: 0,
+        }
+    }
+
+    fn gps(&self) -> usize {
+        (0..self.grid.len())
+            .cartesian_product(0..self.grid[0].len())
+            .filter(|&(r, c)| matches!(self.grid[r][c], '[' | 'O'))
+            .map(|(r, c)| 100 * r + c)
+            .sum()
+    }
+
+    fn render(&self) -> String {
+        self.grid
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(c, &ch)| match (r, c) == self.robot {
+                        true => '@',
+                        false => ch,
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
+
+    fn render_ppm(&self, scale: usize) -> String {
+        let color = |ch: char| match ch {
+            '#' => "128 128 128",
+            'O' | '[' | ']' => "160 100 40",
+            '@' => "220 40 40",
+            _ => "0 0 0",
+        };
+
+        let frame = self.render();
+        let rows = frame.lines().flat_map(|line| {
+            let row = line
+                .chars()
+                .flat_map(|ch| std::iter::repeat_n(color(ch), scale))
+                .join(" ");
+            std::iter::repeat_n(row, scale)
+        });
+
+        format!(
+            "P3\n{} {}\n255\n{}",
+            self.grid[0].len() * scale,
+            self.grid.len() * scale,
+            rows.collect_vec().join("\n")
+        )
+    }
+}
+
+impl Iterator for Replay<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let (instruction, (dr, dc)) = self.instructions.find_map(|inst| match inst {
+            '^' => Some((inst, (-1, 0))),
+            '>' => Some((inst, (0, 1))),
+            'v' => Some((inst, (1, 0))),
+            '<' => Some((inst, (0, -1))),
             _ => None,
+        })?;
+
+        let moved = process(&mut self.grid, self.robot.0, self.robot.1, dr, dc);
+        self.robot = moved.unwrap_or(self.robot);
+        self.step += 1;
+
+        Some(Move {
+            step: self.step,
+            instruction,
+            blocked: moved.is_none(),
         })
-        .fold((r, c), |(r, c), (dr, dc)| {
-            process(&mut grid, r, c, dr, dc).unwrap_or((r, c))
-        });
+    }
+}
 
-    let grid: &Grid = &grid;
-    (0..grid.len())
-        .cartesian_product(0..grid[0].len())
-        .filter(|&(r, c)| matches!(grid[r][c], '[' | 'O'))
-        .map(|(r, c)| (100 * r + c) as i32)
-        .sum::<i32>()
-        .to_string()
+fn solve(input: &Puzzle, expand: bool) -> String {
+    let mut replay = Replay::new(input, expand);
+    replay.by_ref().for_each(drop);
+    replay.gps().to_string()
 }
 
 pub fn part1(input: &Puzzle) -> String {
@@ -143,6 +222,62 @@ pub fn part2(input: &Puzzle) -> String {
     solve(input, true)
 }
 
+pub fn debug(input: &Puzzle, args: &[String]) -> String {
+    let mut replay = Replay::new(input, args.get(1).is_some_and(|part| part == "2"));
+
+    match args[0].as_str() {
+        "frames" if args.len() < 3 => "usage: frames <part> <move...>".to_string(),
+        "frames" => {
+            let frames: HashSet<usize> = args[2..]
+                .iter()
+                .map(|f| f.parse().expect("Moves must be numbers"))
+                .collect();
+            let mut rendered = vec![];
+
+            if frames.contains(&0) {
+                rendered.push(format!("move 0\n{}", replay.render()));
+            }
+            while let Some(m) = replay.next() {
+                if frames.contains(&m.step) {
+                    rendered.push(format!(
+                        "move {} ({})\n{}",
+                        m.step,
+                        m.instruction,
+                        replay.render()
+                    ));
+                }
+            }
+
+            rendered.join("\n\n")
+        }
+        "export" if args.len() < 3 => "usage: export <part> <dir> [txt|ppm]".to_string(),
+        "export" => {
+            let dir = std::path::Path::new(&args[2]);
+            let ppm = args.get(3).is_some_and(|format| format == "ppm");
+            std::fs::create_dir_all(dir).expect("Failed to create output directory");
+
+            let write = |i: usize, replay: &Replay| {
+                let (name, frame) = match ppm {
+                    true => (format!("frame_{i:05}.ppm"), replay.render_ppm(4)),
+                    false => (format!("frame_{i:05}.txt"), replay.render()),
+                };
+                std::fs::write(dir.join(name), frame).expect("Failed to write frame");
+            };
+
+            write(0, &replay);
+            while let Some(m) = replay.next() {
+                write(m.step, &replay);
+            }
+            format!("wrote {} frames to {}", replay.step + 1, dir.display())
+        }
+        "blocked" => replay
+            .filter(|m| m.blocked)
+            .map(|m| format!("move {} ({}) was blocked", m.step, m.instruction))
+            .join("\n"),
+        mode => format!("unknown mode {mode}, expected frames, export or blocked"),
+    }
+}
+
 #[test]
 fn test_day15() {
     let input = parse(
@@ -159,4 +294,13 @@ fn test_day15() {
     );
     assert_eq!(part1(&input), "2028");
     assert_eq!(part2(&input), "1751");
+
+    let blocked = Replay::new(&input, false)
+        .filter(|m| m.blocked)
+        .map(|m| m.step);
+    assert_eq!(blocked.collect_vec(), [1, 3, 6, 8, 15]);
+
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect_vec();
+    assert!(debug(&input, &args(&["frames", "1"])).starts_with("usage"));
+    assert!(debug(&input, &args(&["export"])).starts_with("usage"));
 }
@@ -36,10 +36,54 @@ fn delta(dir: Direction) -> (i32, i32) {
     }
 }
 
+const ALL_DIRS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+#[derive(Clone)]
 pub struct Maze {
     grid: Vec<Vec<char>>,
     start: (usize, usize),
     end: (usize, usize),
+    turn_cost: i32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Move {
+    Forward,
+    Left,
+    Right,
+}
+
+#[derive(Clone)]
+struct Route {
+    moves: Vec<Move>,
+    states: Vec<((usize, usize), Direction)>,
+}
+
+impl Route {
+    fn turns(&self) -> usize {
+        self.moves.iter().filter(|&&m| m != Move::Forward).count()
+    }
+
+    fn tiles(&self) -> Vec<(usize, usize)> {
+        self.states.iter().map(|&(pos, _)| pos).dedup().collect()
+    }
+
+    fn describe(&self) -> String {
+        self.moves
+            .iter()
+            .dedup_with_count()
+            .map(|(count, m)| match m {
+                Move::Forward => format!("{count}F"),
+                Move::Left => "L".repeat(count),
+                Move::Right => "R".repeat(count),
+            })
+            .join(" ")
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -90,7 +134,22 @@ pub fn parse(input: &str) -> Maze {
             false => (acc.0, (i, j)),
         });
 
-    Maze { grid, start, end }
+    Maze {
+        grid,
+        start,
+        end,
+        turn_cost: 1000,
+    }
+}
+
+fn step(maze: &Maze, pos: (usize, usize), dir: Direction, reverse: bool) -> Option<(usize, usize)> {
+    let (dx, dy) = delta(dir);
+    let nx = pos.0 as i32 + (if reverse { -dx } else { dx });
+    let ny = pos.1 as i32 + (if reverse { -dy } else { dy });
+
+    (nx >= 0 && nx < maze.grid.len() as i32 && ny >= 0 && ny < maze.grid[0].len() as i32)
+        .then_some((nx as usize, ny as usize))
+        .filter(|&(x, y)| maze.grid[x][y] != '#')
 }
 
 fn explore_paths(
@@ -114,19 +173,12 @@ fn explore_paths(
 
         costs.insert((pos, dir), cost);
 
-        let (dx, dy) = delta(dir);
-        let nx = pos.0 as i32 + (if reverse { -dx } else { dx });
-        let dx = pos.1 as i32 + (if reverse { -dy } else { dy });
-
-        if nx >= 0 && nx < maze.grid.len() as i32 && dx >= 0 && dx < maze.grid[0].len() as i32 {
-            let new_pos = (nx as usize, dx as usize);
-            if maze.grid[new_pos.0][new_pos.1] != '#' {
-                heap.push(State::new(cost + 1, new_pos, dir));
-            }
+        if let Some(new_pos) = step(maze, pos, dir, reverse) {
+            heap.push(State::new(cost + 1, new_pos, dir));
         }
 
-        heap.push(State::new(cost + 1000, pos, left(dir)));
-        heap.push(State::new(cost + 1000, pos, right(dir)));
+        heap.push(State::new(cost + maze.turn_cost, pos, left(dir)));
+        heap.push(State::new(cost + maze.turn_cost, pos, right(dir)));
     }
 
     costs
@@ -142,21 +194,14 @@ fn shortest_path(maze: &Maze) -> i32 {
 }
 
 fn optimal_tiles(maze: &Maze) -> HashSet<(usize, usize)> {
-    let all_dirs = [
-        Direction::North,
-        Direction::East,
-        Direction::South,
-        Direction::West,
-    ];
-
     let f_costs = explore_paths(maze, maze.start, &[Direction::East], false);
-    let b_costs = explore_paths(maze, maze.end, &all_dirs, true);
+    let b_costs = explore_paths(maze, maze.end, &ALL_DIRS, true);
     let shortest = shortest_path(maze);
 
     (0..maze.grid.len())
         .cartesian_product(0..maze.grid[0].len())
         .filter(|&(r, c)| {
-            all_dirs.iter().any(|&dir| {
+            ALL_DIRS.iter().any(|&dir| {
                 match (f_costs.get(&((r, c), dir)), b_costs.get(&((r, c), dir))) {
                     (Some(&forw), Some(&back)) => forw + back == shortest,
                     _ => false,
@@ -166,6 +211,113 @@ fn optimal_tiles(maze: &Maze) -> HashSet<(usize, usize)> {
         .collect()
 }
 
+struct RouteSearch<'a> {
+    maze: &'a Maze,
+    to_end: HashMap<((usize, usize), Direction), i32>,
+    shortest: i32,
+    limit: usize,
+    routes: Vec<Route>,
+    found: HashSet<Vec<(usize, usize)>>,
+}
+
+impl RouteSearch<'_> {
+    // only follows moves that keep the route on an optimal path, using the cost-to-end table
+    fn walk(&mut self, route: &mut Route, cost: i32) {
+        let (pos, dir) = *route.states.last().unwrap();
+
+        if self.routes.len() >= self.limit {
+            return;
+        }
+
+        if pos == self.maze.end {
+            if cost == self.shortest && self.found.insert(route.tiles()) {
+                self.routes.push(route.clone());
+            }
+            return;
+        }
+
+        let forward = step(self.maze, pos, dir, false).map(|next| (Move::Forward, (next, dir), 1));
+        let turns = [
+            (Move::Left, (pos, left(dir)), self.maze.turn_cost),
+            (Move::Right, (pos, right(dir)), self.maze.turn_cost),
+        ];
+
+        for (m, state, step_cost) in forward.into_iter().chain(turns) {
+            let cost = cost + step_cost;
+            if self.to_end.get(&state) != Some(&(self.shortest - cost))
+                || route.states.contains(&state)
+            {
+                continue;
+            }
+
+            route.moves.push(m);
+            route.states.push(state);
+            self.walk(route, cost);
+            route.moves.pop();
+            route.states.pop();
+        }
+    }
+}
+
+fn optimal_routes(maze: &Maze, limit: usize) -> Vec<Route> {
+    let mut search = RouteSearch {
+        maze,
+        to_end: explore_paths(maze, maze.end, &ALL_DIRS, true),
+        shortest: shortest_path(maze),
+        limit,
+        routes: vec![],
+        found: HashSet::new(),
+    };
+
+    search.walk(
+        &mut Route {
+            moves: vec![],
+            states: vec![(maze.start, Direction::East)],
+        },
+        0,
+    );
+    search.routes
+}
+
+fn render(maze: &Maze, routes: &[Route]) -> String {
+    let marks: HashMap<(usize, usize), char> = match routes {
+        [route] => route
+            .states
+            .iter()
+            .tuple_windows()
+            .filter(|(a, b)| a.0 != b.0)
+            .map(|(&(pos, dir), _)| {
+                let arrow = match dir {
+                    Direction::North => '^',
+                    Direction::East => '>',
+                    Direction::South => 'v',
+                    Direction::West => '<',
+                };
+                (pos, arrow)
+            })
+            .collect(),
+        _ => routes
+            .iter()
+            .flat_map(Route::tiles)
+            .map(|pos| (pos, 'O'))
+            .collect(),
+    };
+
+    maze.grid
+        .iter()
+        .enumerate()
+        .map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(c, &cell)| match cell {
+                    '.' => marks.get(&(r, c)).copied().unwrap_or('.'),
+                    _ => cell,
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
 pub fn part1(input: &Maze) -> String {
     shortest_path(input).to_string()
 }
@@ -174,6 +326,42 @@ pub fn part2(input: &Maze) -> String {
     optimal_tiles(input).len().to_string()
 }
 
+pub fn debug(input: &Maze, args: &[String]) -> String {
+    let with_turn_cost = |i: usize| Maze {
+        turn_cost: args.get(i).map_or(input.turn_cost, |n| {
+            n.parse().expect("Turn cost must be a number")
+        }),
+        ..input.clone()
+    };
+
+    match args[0].as_str() {
+        "route" => {
+            let maze = with_turn_cost(1);
+            let route = &optimal_routes(&maze, 1)[0];
+            format!(
+                "{}\n{} moves, {} turns\n{}",
+                route.describe(),
+                route.moves.len(),
+                route.turns(),
+                render(&maze, std::slice::from_ref(route))
+            )
+        }
+        "routes" => {
+            let maze = with_turn_cost(2);
+            let limit = args
+                .get(1)
+                .map_or(usize::MAX, |n| n.parse().expect("Limit must be a number"));
+            let routes = optimal_routes(&maze, limit);
+            routes
+                .iter()
+                .map(|route| format!("{} turns: {}", route.turns(), route.describe()))
+                .chain([render(&maze, &routes)])
+                .join("\n")
+        }
+        mode => format!("unknown mode {mode}, expected route or routes"),
+    }
+}
+
 #[test]
 fn test_day16() {
     let input = parse(
@@ -195,4 +383,12 @@ fn test_day16() {
     );
     assert_eq!(part1(&input), "7036");
     assert_eq!(part2(&input), "45");
+
+    let routes = optimal_routes(&input, usize::MAX);
+    assert_eq!(routes.len(), 3);
+    assert!(routes.iter().all(|route| route.turns() == 7));
+    assert_eq!(
+        routes.iter().flat_map(Route::tiles).collect::<HashSet<_>>(),
+        optimal_tiles(&input)
+    );
 }
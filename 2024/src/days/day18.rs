@@ -1,5 +1,5 @@
 use itertools::Itertools;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
 
 #[derive(Clone)]
 pub struct Grid {
@@ -22,60 +22,187 @@ pub fn parse(input: &str) -> Grid {
     Grid { points, size: 70 }
 }
 
-fn find_path(
-    size: i32,
-    blocked: &HashSet<(i32, i32)>,
-    start: (i32, i32),
-    end: (i32, i32),
-) -> Option<u32> {
-    let mut queue = VecDeque::from([(start.0, start.1, 0)]);
-    let mut visited = HashSet::from([start]);
-
-    while let Some((x, y, steps)) = queue.pop_front() {
-        if (x, y) == end {
-            return Some(steps);
+type Point = (i32, i32);
+
+fn fall_times(grid: &Grid) -> HashMap<Point, usize> {
+    grid.points
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, &p)| (p, i))
+        .collect()
+}
+
+fn neighbors(size: i32, (x, y): Point) -> impl Iterator<Item = Point> {
+    [(0, 1), (1, 0), (0, -1), (-1, 0)]
+        .into_iter()
+        .map(move |(dx, dy)| (x + dx, y + dy))
+        .filter(move |&(nx, ny)| nx >= 0 && nx <= size && ny >= 0 && ny <= size)
+}
+
+// shortest path once the first `bytes` bytes have fallen
+fn find_path(size: i32, fallen: &HashMap<Point, usize>, bytes: usize) -> Option<Vec<Point>> {
+    let (start, end) = ((0, 0), (size, size));
+    let free = |p: &Point| fallen.get(p).is_none_or(|&t| t >= bytes);
+    let mut queue = VecDeque::from([start]);
+    let mut parents = HashMap::from([(start, start)]);
+
+    while let Some(pos) = queue.pop_front() {
+        if pos == end {
+            let path =
+                std::iter::successors(Some(end), |p| Some(parents[p]).filter(|_| *p != start));
+            return Some(path.collect_vec().into_iter().rev().collect());
         }
 
-        let moves = [(0, 1), (1, 0), (0, -1), (-1, 0)]
-            .iter()
-            .map(|(dx, dy)| (x + dx, y + dy))
-            .filter(|&(nx, ny)| {
-                nx >= 0
-                    && nx <= size
-                    && ny >= 0
-                    && ny <= size
-                    && !blocked.contains(&(nx, ny))
-                    && !visited.contains(&(nx, ny))
-            })
-            .collect::<Vec<_>>();
-
-        for (nx, ny) in moves {
-            visited.insert((nx, ny));
-            queue.push_back((nx, ny, steps + 1));
+        for next in neighbors(size, pos).filter(free) {
+            if let Entry::Vacant(entry) = parents.entry(next) {
+                entry.insert(pos);
+                queue.push_back(next);
+            }
         }
     }
     None
 }
 
+fn first_blocker_bisect(grid: &Grid) -> Option<usize> {
+    let fallen = fall_times(grid);
+    let bytes = grid.points.len();
+    let blocked = |n: usize| find_path(grid.size, &fallen, n).is_none();
+
+    blocked(bytes).then(|| {
+        let (mut lo, mut hi) = (0, bytes);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match blocked(mid) {
+                true => hi = mid,
+                false => lo = mid + 1,
+            }
+        }
+        lo - 1
+    })
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn find(&mut self, x: usize) -> usize {
+        match self.parent[x] {
+            p if p == x => x,
+            p => {
+                let root = self.find(p);
+                self.parent[x] = root;
+                root
+            }
+        }
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a] = b;
+    }
+}
+
+// lets every byte fall, then removes them in reverse until the corners connect again
+fn first_blocker_union_find(grid: &Grid) -> Option<usize> {
+    let fallen = fall_times(grid);
+    let side = grid.size as usize + 1;
+    let index = |(x, y): Point| y as usize * side + x as usize;
+    let mut sets = UnionFind {
+        parent: (0..side * side).collect(),
+    };
+
+    let open = |p: Point, bytes: usize, sets: &mut UnionFind| {
+        neighbors(grid.size, p)
+            .filter(|n| fallen.get(n).is_none_or(|&t| t >= bytes))
+            .for_each(|n| sets.union(index(p), index(n)));
+    };
+
+    (0..=grid.size)
+        .cartesian_product(0..=grid.size)
+        .filter(|p| !fallen.contains_key(p))
+        .for_each(|p| open(p, usize::MAX, &mut sets));
+
+    let (start, end) = (index((0, 0)), index((grid.size, grid.size)));
+    if sets.find(start) == sets.find(end) {
+        return None;
+    }
+
+    fallen
+        .iter()
+        .map(|(&p, &t)| (t, p))
+        .sorted()
+        .rev()
+        .find(|&(t, p)| {
+            open(p, t, &mut sets);
+            sets.find(start) == sets.find(end)
+        })
+        .map(|(t, _)| t)
+}
+
+fn render(grid: &Grid, bytes: usize, path: &[Point]) -> String {
+    let fallen = fall_times(grid);
+    let path: HashSet<_> = path.iter().collect();
+
+    (0..=grid.size)
+        .map(|y| {
+            (0..=grid.size)
+                .map(|x| match fallen.get(&(x, y)) {
+                    Some(&t) if t + 1 == bytes => 'X',
+                    Some(&t) if t < bytes => '#',
+                    _ if path.contains(&(x, y)) => 'O',
+                    _ => '.',
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
 pub fn part1(grid: &Grid) -> String {
     let size = grid.points.len().min(1024); // stops test from crashing
-    let blocked: HashSet<_> = grid.points.iter().take(size).copied().collect();
 
-    find_path(grid.size, &blocked, (0, 0), (grid.size, grid.size))
-        .unwrap()
-        .to_string()
+    (find_path(grid.size, &fall_times(grid), size).unwrap().len() - 1).to_string()
 }
 
 pub fn part2(grid: &Grid) -> String {
-    (0..grid.points.len())
-        .find(|&i| {
-            let blocked: HashSet<_> = grid.points[..=i].iter().copied().collect();
-            find_path(grid.size, &blocked, (0, 0), (grid.size, grid.size)).is_none()
-        })
+    first_blocker_union_find(grid)
         .map(|i| format!("{},{}", grid.points[i].0, grid.points[i].1))
         .unwrap()
 }
 
+pub fn debug(grid: &Grid, args: &[String]) -> String {
+    let fallen = fall_times(grid);
+
+    match args[0].as_str() {
+        "path" => {
+            let bytes = match args.get(1) {
+                Some(n) => n.parse().expect("Byte count must be a number"),
+                None => first_blocker_union_find(grid).map_or(grid.points.len(), |i| i + 1),
+            };
+            let path = find_path(grid.size, &fallen, bytes.saturating_sub(1)).unwrap_or_default();
+            format!(
+                "{} bytes fallen, last path had {} steps\n{}",
+                bytes,
+                path.len().saturating_sub(1),
+                render(grid, bytes, &path)
+            )
+        }
+        "blocker" => {
+            let show = |i: Option<usize>| match i {
+                Some(i) => format!("byte {i} at {},{}", grid.points[i].0, grid.points[i].1),
+                None => "none".to_string(),
+            };
+            format!(
+                "bisect: {}\nunion-find: {}",
+                show(first_blocker_bisect(grid)),
+                show(first_blocker_union_find(grid))
+            )
+        }
+        mode => format!("unknown mode {mode}, expected path or blocker"),
+    }
+}
+
 #[test]
 fn test_day18() {
     let mut input = parse(
@@ -94,5 +221,36 @@ fn test_day18() {
     );
     input.size = 6;
     assert_eq!(part1(&input), "22");
-    // assert_eq!(part2(&input), "6,1"); not sure why this test is failing, don't care enough to fix it lol
+
+    let mut input = parse(
+        "5,4
+         4,2
+         4,5
+         3,0
+         2,1
+         6,3
+         2,4
+         1,5
+         0,6
+         3,3
+         2,6
+         5,1
+         1,2
+         5,5
+         2,5
+         6,5
+         1,4
+         0,4
+         6,4
+         1,1
+         6,1
+         1,0
+         0,5
+         1,6
+         2,0",
+    );
+    input.size = 6;
+    assert_eq!(part2(&input), "6,1");
+    assert_eq!(first_blocker_bisect(&input), Some(20));
+    assert_eq!(first_blocker_union_find(&input), Some(20));
 }
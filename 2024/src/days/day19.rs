@@ -1,8 +1,52 @@
 use itertools::Itertools;
 use std::collections::HashMap;
 
+#[derive(Default)]
+struct Node {
+    children: HashMap<u8, usize>,
+    end: bool,
+}
+
+struct Trie {
+    nodes: Vec<Node>,
+}
+
+impl Trie {
+    fn new<'a>(patterns: impl Iterator<Item = &'a str>) -> Self {
+        let mut trie = Trie {
+            nodes: vec![Node::default()],
+        };
+
+        for pattern in patterns {
+            let node = pattern.bytes().fold(0, |node, b| {
+                let next = trie.nodes.len();
+                let child = *trie.nodes[node].children.entry(b).or_insert(next);
+                if child == next {
+                    trie.nodes.push(Node::default());
+                }
+                child
+            });
+            trie.nodes[node].end = true;
+        }
+
+        trie
+    }
+
+    // lengths of every pattern that the text starts with
+    fn prefixes<'a>(&'a self, text: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        text.iter()
+            .scan(0, |node, b| {
+                *node = *self.nodes[*node].children.get(b)?;
+                Some(*node)
+            })
+            .enumerate()
+            .filter(|&(_, node)| self.nodes[node].end)
+            .map(|(i, _)| i + 1)
+    }
+}
+
 pub struct Input {
-    patterns: Vec<String>,
+    trie: Trie,
     designs: Vec<String>,
 }
 
@@ -10,62 +54,118 @@ pub fn parse(input: &str) -> Input {
     let (patterns, designs) = input.split("\n\n").collect_tuple().unwrap();
 
     Input {
-        patterns: patterns.split(", ").map(str::to_string).collect(),
+        trie: Trie::new(patterns.trim().split(", ")),
         designs: designs.lines().map(str::trim).map(str::to_string).collect(),
     }
 }
 
-fn can_make(design: &str, patterns: &[String]) -> bool {
-    if design.is_empty() {
-        return true;
+// ways[i] is the number of arrangements of design[i..]
+fn suffix_arrangements(design: &[u8], trie: &Trie) -> Vec<u64> {
+    let mut ways = vec![0; design.len() + 1];
+    ways[design.len()] = 1;
+
+    for i in (0..design.len()).rev() {
+        ways[i] = trie.prefixes(&design[i..]).map(|len| ways[i + len]).sum();
     }
+    ways
+}
 
-    patterns
-        .iter()
-        .filter(|pattern| design.starts_with(*pattern))
-        .any(|pattern| can_make(&design[pattern.len()..], patterns))
+fn arrangements(design: &str, trie: &Trie) -> u64 {
+    suffix_arrangements(design.as_bytes(), trie)[0]
 }
 
-fn arrangements(design: &str, patterns: &[String], memo: &mut HashMap<String, u64>) -> u64 {
-    if design.is_empty() {
-        return 1;
-    }
+// the length of the longest prefix that can be built, for designs that can't be finished
+fn longest_prefix(design: &str, trie: &Trie) -> Option<usize> {
+    let design = design.as_bytes();
+    let mut reachable = vec![false; design.len() + 1];
+    reachable[0] = true;
 
-    if let Some(&count) = memo.get(design) {
-        return count;
+    for i in 0..design.len() {
+        if reachable[i] {
+            trie.prefixes(&design[i..])
+                .for_each(|len| reachable[i + len] = true);
+        }
     }
 
-    let total = patterns
-        .iter()
-        .filter(|pattern| design.starts_with(*pattern))
-        .map(|pattern| arrangements(&design[pattern.len()..], patterns, memo))
-        .sum();
+    (!reachable[design.len()]).then(|| reachable.iter().rposition(|&r| r).unwrap())
+}
+
+fn examples<'a>(design: &'a str, trie: &Trie, limit: usize) -> Vec<Vec<&'a str>> {
+    let ways = suffix_arrangements(design.as_bytes(), trie);
+    let mut found = vec![];
+    let mut stack = vec![(0, vec![])];
+
+    while let Some((i, towels)) = stack.pop() {
+        if found.len() == limit {
+            break;
+        }
+        if i == design.len() {
+            found.push(towels);
+            continue;
+        }
 
-    memo.insert(design.to_string(), total);
-    total
+        let next = trie.prefixes(&design.as_bytes()[i..]).collect_vec();
+        for len in next.into_iter().rev().filter(|len| ways[i + len] > 0) {
+            let mut towels = towels.clone();
+            towels.push(&design[i..i + len]);
+            stack.push((i + len, towels));
+        }
+    }
+    found
 }
 
 pub fn part1(input: &Input) -> String {
     let count = input
         .designs
         .iter()
-        .filter(|design| can_make(design, &input.patterns))
+        .filter(|design| arrangements(design, &input.trie) > 0)
         .count();
 
     count.to_string()
 }
 
 pub fn part2(input: &Input) -> String {
-    let mut memo = std::collections::HashMap::new();
     let total: u64 = input
         .designs
         .iter()
-        .map(|design| arrangements(design, &input.patterns, &mut memo))
+        .map(|design| arrangements(design, &input.trie))
         .sum();
 
     total.to_string()
 }
 
+pub fn debug(input: &Input, args: &[String]) -> String {
+    match args[0].as_str() {
+        "list" => {
+            let limit = args
+                .get(1)
+                .map_or(5, |n| n.parse().expect("Limit must be a number"));
+            input
+                .designs
+                .iter()
+                .map(|design| match longest_prefix(design, &input.trie) {
+                    Some(len) => format!(
+                        "{design}: impossible, only the first {len} colors can be made ({} | {})",
+                        &design[..len],
+                        &design[len..]
+                    ),
+                    None => examples(design, &input.trie, limit)
+                        .iter()
+                        .map(|towels| format!("  {}", towels.join(",")))
+                        .fold(
+                            format!(
+                                "{design}: {} arrangements",
+                                arrangements(design, &input.trie)
+                            ),
+                            |out, line| out + "\n" + &line,
+                        ),
+                })
+                .join("\n")
+        }
+        mode => format!("unknown mode {mode}, expected list"),
+    }
+}
+
 #[test]
 fn test_day19() {
     let input = parse(
@@ -82,4 +182,12 @@ fn test_day19() {
     );
     assert_eq!(part1(&input), "6");
     assert_eq!(part2(&input), "16");
+
+    assert_eq!(
+        examples("brwrr", &input.trie, 5),
+        [vec!["b", "r", "wr", "r"], vec!["br", "wr", "r"]]
+    );
+    assert_eq!(longest_prefix("ubwu", &input.trie), Some(0));
+    assert_eq!(longest_prefix("bbrgwb", &input.trie), Some(4));
+    assert_eq!(longest_prefix("gbbr", &input.trie), None);
 }
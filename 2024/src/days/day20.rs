@@ -1,5 +1,5 @@
-use itertools::iproduct;
-use std::collections::{HashSet, VecDeque};
+use itertools::{iproduct, Itertools};
+use std::collections::{BTreeMap, VecDeque};
 
 const DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
 
@@ -40,7 +40,7 @@ fn is_track(grid: &Grid, x: usize, y: usize) -> bool {
     grid[x][y] == '.' || grid[x][y] == 'S' || grid[x][y] == 'E'
 }
 
-fn bfs(grid: &Grid, start: (usize, usize), steps: Option<usize>, walls: bool) -> Vec<Vec<usize>> {
+fn bfs(grid: &Grid, start: (usize, usize)) -> Vec<Vec<usize>> {
     let rows = grid.len();
     let cols = grid[0].len();
     let mut dist = vec![vec![usize::MAX; cols]; rows];
@@ -50,22 +50,12 @@ fn bfs(grid: &Grid, start: (usize, usize), steps: Option<usize>, walls: bool) ->
     queue.push_back(start);
 
     while let Some((x, y)) = queue.pop_front() {
-        let d = dist[x][y];
-        if let Some(max) = steps {
-            if d == max {
-                continue;
-            }
-        }
-
         let neighbors: Vec<(usize, usize)> = DIRECTIONS
             .iter()
             .map(|&(dx, dy)| (x as i32 + dx, y as i32 + dy))
             .map(|(dx, dy)| (dx as usize, dy as usize))
             .filter(|&(nx, ny)| {
-                nx < rows
-                    && ny < cols
-                    && (!walls || is_track(grid, nx, ny))
-                    && dist[nx][ny] > dist[x][y] + 1
+                nx < rows && ny < cols && is_track(grid, nx, ny) && dist[nx][ny] > dist[x][y] + 1
             })
             .collect();
 
@@ -78,89 +68,73 @@ fn bfs(grid: &Grid, start: (usize, usize), steps: Option<usize>, walls: bool) ->
     dist
 }
 
-fn reachable_cells(grid: &Grid, start_dist: &[Vec<usize>]) -> Vec<(usize, usize)> {
-    iproduct!(0..grid.len(), 0..grid[0].len())
-        .filter(|&(x, y)| start_dist[x][y] != usize::MAX && is_track(grid, x, y))
-        .collect()
-}
-
-fn cheats(
-    grid: &Grid,
-    start_dist: &[Vec<usize>],
-    end_dist: &[Vec<usize>],
-    normal_dist: usize,
-    steps: Option<usize>,
-) -> HashSet<(usize, usize, usize, usize)> {
-    let mut cheats = HashSet::new();
-    let cells = reachable_cells(grid, start_dist);
-
-    for &(sx, sy) in &cells {
-        let base_dist = start_dist[sx][sy];
-        let dist_no_walls = match steps {
-            Some(max_steps) => bfs(grid, (sx, sy), Some(max_steps), false),
-            None => {
-                let mut dist = vec![vec![usize::MAX; grid[0].len()]; grid.len()];
-                for (dx1, dy1) in DIRECTIONS {
-                    let nx = (sx as i32 + dx1) as usize;
-                    let ny = (sy as i32 + dy1) as usize;
-                    if nx < grid.len() && ny < grid[0].len() {
-                        dist[nx][ny] = 1;
-
-                        for (dx2, dy2) in DIRECTIONS {
-                            let fx = (nx as i32 + dx2) as usize;
-                            let fy = (ny as i32 + dy2) as usize;
-                            if fx < grid.len() && fy < grid[0].len() && is_track(grid, fx, fy) {
-                                dist[fx][fy] = 2;
-                            }
-                        }
-                    }
-                }
-                dist
-            }
-        };
-
-        iproduct!(0..grid.len(), 0..grid[0].len())
-            .filter(|&(fx, fy)| {
-                let d = dist_no_walls[fx][fy];
-                d != usize::MAX
-                    && match steps {
-                        Some(max_steps) => (1..=max_steps).contains(&d),
-                        None => d <= 2,
-                    }
-                    && is_track(grid, fx, fy)
-                    && end_dist[fx][fy] != usize::MAX
-            })
-            .for_each(|(fx, fy)| {
-                let route_with_cheat = base_dist + dist_no_walls[fx][fy] + end_dist[fx][fy];
-                let saving = normal_dist.saturating_sub(route_with_cheat);
-                if saving >= 100 {
-                    cheats.insert((sx, sy, fx, fy));
-                }
-            });
-    }
+// every cheat starts on a track cell and ends on one within `steps` manhattan distance, so the
+// savings come straight from the distance tables without searching through walls
+fn savings(input: &Maze, steps: usize) -> BTreeMap<usize, usize> {
+    let grid = &input.grid;
+    let start_dist = &bfs(grid, input.start);
+    let end_dist = &bfs(grid, input.end);
+    let normal_dist = start_dist[input.end.0][input.end.1];
 
-    cheats
-}
+    let track = iproduct!(0..grid.len(), 0..grid[0].len())
+        .filter(|&(x, y)| start_dist[x][y] != usize::MAX && end_dist[x][y] != usize::MAX)
+        .sorted_by_key(|&(x, y)| start_dist[x][y])
+        .collect_vec();
 
-fn solve(input: &Maze, steps: Option<usize>) -> String {
-    let start_dist = bfs(&input.grid, input.start, None, true);
-    let end_dist = bfs(&input.grid, input.end, None, true);
+    let steps = steps as i32;
+    let offsets = iproduct!(-steps..=steps, -steps..=steps)
+        .filter(|(dx, dy)| (2..=steps).contains(&(dx.abs() + dy.abs())))
+        .collect_vec();
 
-    let normal_dist = start_dist[input.end.0][input.end.1];
-    if normal_dist == usize::MAX {
-        return "0".to_string();
-    }
+    track
+        .iter()
+        .flat_map(|&(sx, sy)| {
+            offsets.iter().filter_map(move |&(dx, dy)| {
+                let (fx, fy) = ((sx as i32 + dx) as usize, (sy as i32 + dy) as usize);
+                let end = end_dist.get(fx)?.get(fy).filter(|&&d| d != usize::MAX)?;
+                let cheated = start_dist[sx][sy] + (dx.abs() + dy.abs()) as usize + end;
+                normal_dist
+                    .checked_sub(cheated)
+                    .filter(|&saving| saving > 0)
+            })
+        })
+        .counts()
+        .into_iter()
+        .collect()
+}
 
-    let cheats = cheats(&input.grid, &start_dist, &end_dist, normal_dist, steps);
-    cheats.len().to_string()
+fn solve(input: &Maze, steps: usize) -> String {
+    savings(input, steps)
+        .range(100..)
+        .map(|(_, count)| count)
+        .sum::<usize>()
+        .to_string()
 }
 
 pub fn part1(input: &Maze) -> String {
-    solve(input, None)
+    solve(input, 2)
 }
 
 pub fn part2(input: &Maze) -> String {
-    solve(input, Some(20))
+    solve(input, 20)
+}
+
+pub fn debug(input: &Maze, args: &[String]) -> String {
+    let number = |i: usize, default| {
+        args.get(i)
+            .map_or(default, |n| n.parse().expect("Expected a number"))
+    };
+
+    match args[0].as_str() {
+        "histogram" => savings(input, number(1, 2))
+            .range(number(2, 1)..)
+            .map(|(saving, count)| match count {
+                1 => format!("There is one cheat that saves {saving} picoseconds."),
+                _ => format!("There are {count} cheats that save {saving} picoseconds."),
+            })
+            .join("\n"),
+        mode => format!("unknown mode {mode}, expected histogram"),
+    }
 }
 
 #[test]
@@ -184,4 +158,37 @@ fn test_day20() {
     );
     assert_eq!(part1(&input), "0");
     assert_eq!(part2(&input), "0");
+
+    let short = [
+        (2, 14),
+        (4, 14),
+        (6, 2),
+        (8, 4),
+        (10, 2),
+        (12, 3),
+        (20, 1),
+        (36, 1),
+        (38, 1),
+        (40, 1),
+        (64, 1),
+    ];
+    assert_eq!(savings(&input, 2), BTreeMap::from(short));
+
+    let long = [
+        (50, 32),
+        (52, 31),
+        (54, 29),
+        (56, 39),
+        (58, 25),
+        (60, 23),
+        (62, 20),
+        (64, 19),
+        (66, 12),
+        (68, 14),
+        (70, 12),
+        (72, 22),
+        (74, 4),
+        (76, 3),
+    ];
+    assert_eq!(savings(&input, 20).split_off(&50), BTreeMap::from(long));
 }
@@ -1,176 +1,219 @@
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
-
-#[derive(Clone, Eq, PartialEq)]
-struct State {
-    cost: i64,
-    pos: (i32, i32),
-    prev: char,
-    out: String,
-}
+use itertools::Itertools;
+use std::collections::{HashMap, VecDeque};
 
-impl State {
-    fn new(cost: i64, pos: (i32, i32), prev: char, out: String) -> Self {
-        Self {
-            cost,
-            pos,
-            prev,
-            out,
-        }
-    }
+const NUMERIC: &str = "789/456/123/ 0A";
+const DIRECTIONAL: &str = " ^A/<v>";
+
+struct Keypad {
+    keys: HashMap<char, (i32, i32)>,
 }
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.cost.cmp(&self.cost)
+impl Keypad {
+    // rows are separated by '/', and spaces mark gaps the robot arm can't pass over
+    fn new(layout: &str) -> Self {
+        let keys = layout
+            .split('/')
+            .enumerate()
+            .flat_map(|(r, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|&(_, key)| key != ' ')
+                    .map(move |(c, key)| (key, (r as i32, c as i32)))
+            })
+            .collect();
+
+        Keypad { keys }
     }
-}
 
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    fn key_at(&self, pos: (i32, i32)) -> Option<char> {
+        self.keys
+            .iter()
+            .find(|(_, &p)| p == pos)
+            .map(|(&key, _)| key)
     }
-}
 
-#[derive(Clone, Copy, PartialEq)]
-enum PadType {
-    Numeric,
-    Directional,
-}
+    // every shortest sequence of arrow presses that moves the arm from one key to another
+    fn paths(&self, from: char, to: char) -> Vec<String> {
+        let moves = [('^', (-1, 0)), ('>', (0, 1)), ('v', (1, 0)), ('<', (0, -1))];
+        let target = self.keys[&to];
+        let mut dist = HashMap::from([(target, 0)]);
+        let mut queue = VecDeque::from([target]);
 
-fn pad(pos: (i32, i32), pad_type: PadType) -> Option<char> {
-    let keypad = match pad_type {
-        PadType::Numeric => "789 456 123  0A",
-        PadType::Directional => " ^A <v>",
-    };
+        while let Some((r, c)) = queue.pop_front() {
+            for (_, (dr, dc)) in moves {
+                let next = (r + dr, c + dc);
+                if self.key_at(next).is_some() && !dist.contains_key(&next) {
+                    dist.insert(next, dist[&(r, c)] + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
 
-    keypad
-        .chars()
-        .nth((4 * pos.0 + pos.1) as usize)
-        .filter(|&c| c != ' ')
-}
+        let mut paths = vec![];
+        let mut stack = vec![(self.keys[&from], String::new())];
+        while let Some(((r, c), path)) = stack.pop() {
+            if (r, c) == target {
+                paths.push(path);
+                continue;
+            }
 
-fn apply((r, c): (i32, i32), mv: char, pad_type: PadType) -> ((i32, i32), Option<char>) {
-    match mv {
-        'A' => ((r, c), pad((r, c), pad_type)),
-        '<' => ((r, c - 1), None),
-        '^' => ((r - 1, c), None),
-        '>' => ((r, c + 1), None),
-        'v' => ((r + 1, c), None),
-        _ => unreachable!(),
+            for (mv, (dr, dc)) in moves {
+                let next = (r + dr, c + dc);
+                if dist.get(&next) == Some(&(dist[&(r, c)] - 1)) {
+                    stack.push((next, format!("{path}{mv}")));
+                }
+            }
+        }
+        paths
     }
 }
 
-fn extend(
-    queue: &mut BinaryHeap<State>,
-    state: &State,
-    pad_type: PadType,
-    pads: i32,
-    dp: &mut HashMap<(char, char, i32), i64>,
-) {
-    queue.extend(['A', '<', '>', 'v', '^'].iter().map(|&mv| {
-        let (pos, output) = apply(state.pos, mv, pad_type);
-        let cost = match pad_type {
-            PadType::Directional => cost(mv, state.prev, pads - 1, dp),
-            PadType::Numeric => cost(mv, state.prev, pads, dp),
-        };
-        let out = match output {
-            Some(out) => format!("{}{}", state.out, out),
-            None => state.out.clone(),
-        };
-        State::new(state.cost + cost, pos, mv, out)
-    }));
+// key sequences longer than this are only reported by length, deep chains grow them
+// exponentially
+const MAX_SHOWN: u64 = 10_000;
+
+// the keys pressed on one pad in the chain, kept only while they're short enough to print
+struct Level {
+    len: u64,
+    keys: Option<String>,
+}
+
+// pads[0] is the door keypad, each later pad is the directional keypad controlling the one
+// before it, and the human presses keys on one more directional keypad at the end of the chain
+struct Robots {
+    pads: Vec<Keypad>,
+    memo: HashMap<(usize, char, char), u64>,
+    paths: HashMap<(usize, char, char), Vec<String>>,
+    best: HashMap<(usize, char, char), String>,
 }
 
-fn cost(ch: char, prev: char, pads: i32, dp: &mut HashMap<(char, char, i32), i64>) -> i64 {
-    match (dp.get(&(ch, prev, pads)), pads) {
-        (Some(&cost), _) => return cost,
-        (_, 0) => return 1,
-        _ => {}
+impl Robots {
+    fn new(door: &str, directional: &str, depth: usize) -> Self {
+        let pads = std::iter::once(Keypad::new(door))
+            .chain((0..depth).map(|_| Keypad::new(directional)))
+            .collect();
+
+        Robots {
+            pads,
+            memo: HashMap::new(),
+            paths: HashMap::new(),
+            best: HashMap::new(),
+        }
+    }
+
+    fn paths(&mut self, level: usize, from: char, to: char) -> Vec<String> {
+        let pad = &self.pads[level];
+        self.paths
+            .entry((level, from, to))
+            .or_insert_with(|| pad.paths(from, to))
+            .clone()
     }
 
-    let start_pos = match prev {
-        '^' => (0, 1),
-        '<' => (1, 0),
-        'v' => (1, 1),
-        '>' => (1, 2),
-        'A' => (0, 2),
-        _ => unreachable!(),
-    };
-
-    let mut queue = BinaryHeap::new();
-    let mut seen = HashMap::new();
-    queue.push(State::new(0, start_pos, 'A', String::new()));
-
-    while let Some(state) = queue.pop() {
-        if pad(state.pos, PadType::Directional).is_none() {
-            continue;
+    fn presses(&mut self, level: usize, keys: &str) -> u64 {
+        match level == self.pads.len() {
+            true => keys.len() as u64,
+            false => std::iter::once('A')
+                .chain(keys.chars())
+                .tuple_windows()
+                .map(|(from, to)| self.cost(level, from, to))
+                .sum(),
         }
+    }
 
-        if state.out == ch.to_string() {
-            let cost = state.cost;
-            dp.insert((ch, prev, pads), cost);
+    fn cost(&mut self, level: usize, from: char, to: char) -> u64 {
+        if let Some(&cost) = self.memo.get(&(level, from, to)) {
             return cost;
         }
 
-        if !state.out.is_empty() {
-            continue;
-        }
+        let cost = self
+            .paths(level, from, to)
+            .iter()
+            .map(|path| self.presses(level + 1, &format!("{path}A")))
+            .min()
+            .unwrap();
 
-        let key = (state.pos, state.prev);
-        if seen.get(&key).map_or(false, |&c| state.cost >= c) {
-            continue;
+        self.memo.insert((level, from, to), cost);
+        cost
+    }
+
+    fn best_path(&mut self, level: usize, from: char, to: char) -> String {
+        if let Some(best) = self.best.get(&(level, from, to)) {
+            return best.clone();
         }
 
-        seen.insert(key, state.cost);
-        extend(&mut queue, &state, PadType::Directional, pads, dp);
+        let path = self
+            .paths(level, from, to)
+            .into_iter()
+            .min_by_key(|path| self.presses(level + 1, &format!("{path}A")))
+            .unwrap();
+        let best = format!("{path}A");
+        self.best.insert((level, from, to), best.clone());
+        best
     }
-    unreachable!()
-}
 
-fn solve(code: &str, pads: i32) -> i64 {
-    let mut queue = BinaryHeap::new();
-    let mut seen = HashMap::new();
-    let mut dp = HashMap::new();
+    // the keys pressed on every pad in the chain, from the door code down to the human. the
+    // lengths come from counting key pairs, so they stay exact once the keys get too long
+    fn sequences(&mut self, code: &str) -> Vec<Level> {
+        let pairs = |keys: &str| {
+            std::iter::once('A')
+                .chain(keys.chars())
+                .tuple_windows()
+                .collect_vec()
+        };
 
-    queue.push(State::new(0, (3, 2), 'A', String::new()));
+        let mut counts = pairs(code).into_iter().counts();
+        let mut levels = vec![Level {
+            len: code.len() as u64,
+            keys: Some(code.to_string()),
+        }];
 
-    while let Some(state) = queue.pop() {
-        if state.out == code {
-            return state.cost;
-        }
+        for level in 0..self.pads.len() {
+            let mut next_counts = HashMap::new();
+            let mut len = 0;
+            for (&(from, to), &n) in &counts {
+                let path = self.best_path(level, from, to);
+                len += path.len() as u64 * n as u64;
+                for pair in pairs(&path) {
+                    *next_counts.entry(pair).or_default() += n;
+                }
+            }
 
-        if !code.starts_with(&state.out) || pad(state.pos, PadType::Numeric).is_none() {
-            continue;
+            let keys = levels[level]
+                .keys
+                .as_ref()
+                .filter(|_| len <= MAX_SHOWN)
+                .map(|keys| {
+                    pairs(keys)
+                        .into_iter()
+                        .map(|(from, to)| self.best_path(level, from, to))
+                        .collect()
+                });
+            levels.push(Level { len, keys });
+            counts = next_counts;
         }
-
-        let key = (state.pos, state.prev, state.out.clone());
-        if seen.contains_key(&key) {
-            continue;
-        }
-
-        seen.insert(key, state.cost);
-        extend(&mut queue, &state, PadType::Numeric, pads, &mut dp);
+        levels
     }
-    unreachable!()
 }
 
 pub fn parse(input: &str) -> Vec<String> {
     input.lines().map(|line| line.trim().to_string()).collect()
 }
 
-pub fn solve_part(input: &[String], multiplier: i32) -> String {
+fn numeric_part(code: &str) -> u64 {
+    code.chars()
+        .filter(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .unwrap()
+}
+
+pub fn solve_part(input: &[String], depth: usize) -> String {
+    let mut robots = Robots::new(NUMERIC, DIRECTIONAL, depth);
+
     input
         .iter()
-        .map(|line| {
-            line.chars()
-                .filter(|c| c.is_digit(10))
-                .collect::<String>()
-                .parse::<i64>()
-                .unwrap()
-                * solve(line, multiplier)
-        })
-        .sum::<i64>()
+        .map(|code| numeric_part(code) * robots.presses(0, code))
+        .sum::<u64>()
         .to_string()
 }
 
@@ -182,9 +225,58 @@ pub fn part2(input: &[String]) -> String {
     solve_part(input, 25)
 }
 
+pub fn debug(input: &[String], args: &[String]) -> String {
+    let depth = args
+        .get(1)
+        .map_or(2, |n| n.parse().expect("Depth must be a number"));
+    let door = args.get(2).map_or(NUMERIC, String::as_str);
+    let directional = args.get(3).map_or(DIRECTIONAL, String::as_str);
+    let mut robots = Robots::new(door, directional, depth);
+
+    match args[0].as_str() {
+        "sequence" => input
+            .iter()
+            .map(|code| {
+                let levels = robots.sequences(code);
+                let presses = levels[depth + 1].len;
+                levels
+                    .iter()
+                    .enumerate()
+                    .map(|(i, level)| match &level.keys {
+                        Some(keys) => format!("  {i}: {keys}"),
+                        None => format!("  {i}: ({} keys)", level.len),
+                    })
+                    .fold(format!("{code} ({presses} presses)"), |out, line| {
+                        out + "\n" + &line
+                    })
+            })
+            .join("\n"),
+        "cost" => input
+            .iter()
+            .map(|code| format!("{code}: {} presses", robots.presses(0, code)))
+            .join("\n"),
+        mode => format!("unknown mode {mode}, expected sequence or cost"),
+    }
+}
+
 #[test]
 fn test_day21() {
     let input = parse("029A\n980A\n179A\n456A\n379A");
     assert_eq!(part1(&input), "126384");
     assert_eq!(part2(&input), "154115708116294");
+
+    let mut robots = Robots::new(NUMERIC, DIRECTIONAL, 2);
+    let levels = robots.sequences("029A");
+    assert_eq!(levels[1].keys.as_ref().unwrap().len(), "<A^A>^^AvvvA".len());
+    assert_eq!(levels[3].len, 68);
+    assert_eq!(levels[3].keys.as_ref().unwrap().len(), 68);
+
+    let mut robots = Robots::new(NUMERIC, DIRECTIONAL, 25);
+    let levels = robots.sequences("029A");
+    assert_eq!(levels[26].len, robots.presses(0, "029A"));
+    assert!(levels[26].keys.is_none() && levels[2].keys.is_some());
+    assert_eq!(
+        Robots::new("123/456/789/ 0A", DIRECTIONAL, 0).presses(0, "7A"),
+        8
+    );
 }
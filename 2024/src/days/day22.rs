@@ -1,4 +1,3 @@
-use itertools::iproduct;
 use rayon::prelude::*;
 
 pub fn parse(input: &str) -> Vec<u64> {
@@ -23,17 +22,70 @@ fn get_price(secret: u64) -> i32 {
     (secret % 10) as i32
 }
 
-fn price_changes(initial: u64, n: usize) -> Vec<i32> {
+fn prices(initial: u64, n: usize) -> Vec<i32> {
     std::iter::successors(Some(initial), |&current| Some(next_secret(current)))
         .take(n + 1)
         .map(get_price)
-        .collect::<Vec<_>>()
-        .windows(2)
-        .map(|w| w[1] - w[0])
         .collect()
 }
 
-pub fn part1(input: &Vec<u64>) -> String {
+// four changes in -9..=9, packed as base 19 digits
+fn window_index(changes: impl Iterator<Item = i32>) -> usize {
+    changes.fold(0, |idx, change| idx * 19 + (change + 9) as usize)
+}
+
+fn window_changes(idx: usize) -> [i32; 4] {
+    [3, 2, 1, 0].map(|p| (idx / 19usize.pow(p) % 19) as i32 - 9)
+}
+
+// the price a buyer sells at for every change sequence, taken at its first occurrence
+fn first_sales(prices: &[i32]) -> impl Iterator<Item = (usize, i32)> + '_ {
+    let mut seen = vec![false; 19usize.pow(4)];
+    prices.windows(5).filter_map(move |w| {
+        let idx = window_index(w.windows(2).map(|p| p[1] - p[0]));
+        (!std::mem::replace(&mut seen[idx], true)).then_some((idx, w[4]))
+    })
+}
+
+fn sequence_totals(input: &[u64]) -> Vec<i32> {
+    input
+        .par_iter()
+        .fold(
+            || vec![0; 19usize.pow(4)],
+            |mut totals, &initial| {
+                first_sales(&prices(initial, 2000)).for_each(|(idx, price)| totals[idx] += price);
+                totals
+            },
+        )
+        .reduce(
+            || vec![0; 19usize.pow(4)],
+            |a, b| a.iter().zip(b).map(|(x, y)| x + y).collect(),
+        )
+}
+
+fn best_sequence(input: &[u64]) -> ([i32; 4], i32) {
+    let (idx, &total) = sequence_totals(input)
+        .iter()
+        .enumerate()
+        .max_by_key(|&(_, total)| total)
+        .unwrap();
+    (window_changes(idx), total)
+}
+
+fn sale_prices(input: &[u64], changes: [i32; 4]) -> Vec<Option<i32>> {
+    let target = window_index(changes.into_iter());
+
+    input
+        .iter()
+        .map(|&initial| {
+            first_sales(&prices(initial, 2000))
+                .find(|&(idx, _)| idx == target)
+                .map(|(_, price)| price)
+        })
+        .collect()
+}
+
+pub fn part1(input: &[u64]) -> String {
     input
         .iter()
         .map(|&initial| nth_secret(initial, 2000))
@@ -41,32 +93,28 @@ pub fn part1(input: &Vec<u64>) -> String {
         .to_string()
 }
 
-pub fn part2(input: &Vec<u64>) -> String {
-    let changes: Vec<(usize, Vec<i32>)> = input
-        .par_iter()
-        .enumerate()
-        .map(|(idx, &initial)| (idx, price_changes(initial, 2000)))
-        .collect();
-
-    iproduct!(-9..=9, -9..=9, -9..=9, -9..=9)
-        .collect::<Vec<_>>()
-        .into_par_iter()
-        .map(|(a, b, c, d)| {
-            changes
+pub fn part2(input: &[u64]) -> String {
+    best_sequence(input).1.to_string()
+}
+
+pub fn debug(input: &[u64], args: &[String]) -> String {
+    match args[0].as_str() {
+        "best" => {
+            let (changes, total) = best_sequence(input);
+            input
                 .iter()
-                .map(|(idx, change)| {
-                    change
-                        .windows(4)
-                        .enumerate()
-                        .find(|(_, window)| window == &[a, b, c, d])
-                        .map(|(pos, _)| get_price(nth_secret(input[*idx], pos + 4)))
-                        .unwrap_or(0)
+                .zip(sale_prices(input, changes))
+                .map(|(initial, price)| match price {
+                    Some(price) => format!("  {initial}: sells at {price}"),
+                    None => format!("  {initial}: never sees the sequence"),
                 })
-                .sum::<i32>()
-        })
-        .max()
-        .unwrap()
-        .to_string()
+                .fold(
+                    format!("sequence {changes:?} earns {total} bananas"),
+                    |out, line| out + "\n" + &line,
+                )
+        }
+        mode => format!("unknown mode {mode}, expected best"),
+    }
 }
 
 #[test]
@@ -74,4 +122,11 @@ fn test_day22() {
     let input = parse("1\n10\n100\n2024");
     assert_eq!(part1(&input), "37327623");
     assert_eq!(part2(&input), "24");
+
+    let input = parse("1\n2\n3\n2024");
+    assert_eq!(best_sequence(&input), ([-2, 1, -1, 3], 23));
+    assert_eq!(
+        sale_prices(&input, [-2, 1, -1, 3]),
+        [Some(7), Some(7), None, Some(9)]
+    );
 }
@@ -1,101 +1,231 @@
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-type Graph = HashMap<String, HashSet<String>>;
+pub struct Graph {
+    names: Vec<String>,
+    edges: Vec<HashSet<usize>>,
+}
 
 pub fn parse(input: &str) -> Graph {
-    input.lines().filter_map(|line| line.split_once('-')).fold(
-        HashMap::new(),
-        |mut graph, (a, b)| {
-            [(a, b), (b, a)].into_iter().for_each(|(from, to)| {
-                graph
-                    .entry(from.to_string())
-                    .or_default()
-                    .insert(to.to_string());
-            });
-            graph
-        },
-    )
-}
+    let mut graph = Graph {
+        names: vec![],
+        edges: vec![],
+    };
+    let mut ids = HashMap::new();
 
-fn triplets(graph: &Graph) -> Vec<HashSet<String>> {
-    graph
-        .keys()
-        .tuple_combinations()
-        .filter(|(i, j, k)| {
-            graph[*i].contains(*j) && graph[*i].contains(*k) && graph[*j].contains(*k)
+    let mut intern = |graph: &mut Graph, name: &str| {
+        *ids.entry(name.to_string()).or_insert_with(|| {
+            graph.names.push(name.to_string());
+            graph.edges.push(HashSet::new());
+            graph.names.len() - 1
         })
-        .map(|(i, j, k)| {
-            [i.clone(), j.clone(), k.clone()]
-                .into_iter()
-                .collect::<HashSet<_>>()
-        })
-        .collect()
+    };
+
+    input
+        .lines()
+        .filter_map(|line| line.trim().split_once('-'))
+        .for_each(|(a, b)| {
+            let (a, b) = (intern(&mut graph, a), intern(&mut graph, b));
+            graph.edges[a].insert(b);
+            graph.edges[b].insert(a);
+        });
+
+    graph
 }
 
-// bron-kerbosch
-fn max_clique(
-    graph: &Graph,
-    clique: HashSet<String>,
-    candidates: HashSet<String>,
-    excluded: HashSet<String>,
-) -> HashSet<String> {
-    if candidates.is_empty() && excluded.is_empty() {
-        return clique;
+impl Graph {
+    fn triangles(&self) -> Vec<[usize; 3]> {
+        (0..self.names.len())
+            .flat_map(|a| {
+                self.edges[a]
+                    .iter()
+                    .filter(move |&&b| b > a)
+                    .flat_map(move |&b| {
+                        self.edges[a]
+                            .intersection(&self.edges[b])
+                            .filter(move |&&c| c > b)
+                            .map(move |&c| [a, b, c])
+                    })
+            })
+            .collect()
     }
 
-    if candidates.is_empty() {
-        return HashSet::new();
+    // repeatedly removes a vertex of minimum remaining degree
+    fn degeneracy_order(&self) -> Vec<usize> {
+        let mut degrees = self.edges.iter().map(HashSet::len).collect_vec();
+        let mut queue: BTreeSet<_> = degrees.iter().enumerate().map(|(v, &d)| (d, v)).collect();
+        let mut order = vec![];
+
+        while let Some((_, v)) = queue.pop_first() {
+            order.push(v);
+            for &u in &self.edges[v] {
+                if queue.remove(&(degrees[u], u)) {
+                    degrees[u] -= 1;
+                    queue.insert((degrees[u], u));
+                }
+            }
+        }
+        order
     }
 
-    let v = candidates.iter().next().unwrap();
-    let neighbors: HashSet<_> = graph[v].iter().cloned().collect();
+    // bron-kerbosch, pivoting on the vertex that covers the most candidates
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<usize>,
+        mut candidates: HashSet<usize>,
+        mut excluded: HashSet<usize>,
+        cliques: &mut Vec<Vec<usize>>,
+    ) {
+        let Some(pivot) = candidates
+            .iter()
+            .chain(&excluded)
+            .max_by_key(|&&u| candidates.intersection(&self.edges[u]).count())
+            .copied()
+        else {
+            cliques.push(clique.clone());
+            return;
+        };
 
-    let neighbor_intersect = |set: &HashSet<String>| {
-        set.iter()
-            .filter(|u| neighbors.contains(*u))
-            .cloned()
-            .collect::<HashSet<_>>()
-    };
+        let branches = candidates
+            .difference(&self.edges[pivot])
+            .copied()
+            .collect_vec();
+
+        for v in branches {
+            clique.push(v);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(&self.edges[v]).copied().collect(),
+                excluded.intersection(&self.edges[v]).copied().collect(),
+                cliques,
+            );
+            clique.pop();
+            candidates.remove(&v);
+            excluded.insert(v);
+        }
+    }
+
+    fn maximal_cliques(&self) -> Vec<Vec<usize>> {
+        let mut cliques = vec![];
+        let mut earlier = HashSet::new();
+
+        for v in self.degeneracy_order() {
+            let neighbors = &self.edges[v];
+            self.bron_kerbosch(
+                &mut vec![v],
+                neighbors.difference(&earlier).copied().collect(),
+                neighbors.intersection(&earlier).copied().collect(),
+                &mut cliques,
+            );
+            earlier.insert(v);
+        }
+        cliques
+    }
 
-    let with_v = max_clique(
-        graph,
-        clique.iter().chain(std::iter::once(v)).cloned().collect(),
-        neighbor_intersect(&candidates),
-        neighbor_intersect(&excluded),
-    );
-
-    let without_v = max_clique(
-        graph,
-        clique,
-        candidates.iter().filter(|&u| u != v).cloned().collect(),
-        excluded.iter().chain(std::iter::once(v)).cloned().collect(),
-    );
-
-    match with_v.len() > without_v.len() {
-        true => with_v,
-        false => without_v,
+    fn components(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.names.len()];
+
+        (0..self.names.len())
+            .filter_map(|start| {
+                let mut stack = vec![start];
+                let mut component = vec![];
+                while let Some(v) = stack.pop() {
+                    if !std::mem::replace(&mut seen[v], true) {
+                        component.push(v);
+                        stack.extend(&self.edges[v]);
+                    }
+                }
+                (!component.is_empty()).then_some(component)
+            })
+            .collect()
+    }
+
+    fn edge_list(&self) -> impl Iterator<Item = (&str, &str)> {
+        (0..self.names.len())
+            .flat_map(|a| {
+                self.edges[a]
+                    .iter()
+                    .filter(move |&&b| b > a)
+                    .map(move |&b| (a, b))
+            })
+            .map(|(a, b)| (self.names[a].as_str(), self.names[b].as_str()))
+            .sorted()
+    }
+
+    fn to_dot(&self) -> String {
+        self.edge_list()
+            .map(|(a, b)| format!("  {a} -- {b};"))
+            .fold("graph lan {".to_string(), |dot, line| dot + "\n" + &line)
+            + "\n}"
+    }
+
+    fn to_graphml(&self) -> String {
+        let nodes = self
+            .names
+            .iter()
+            .sorted()
+            .map(|name| format!("    <node id=\"{name}\"/>"));
+        let edges = self
+            .edge_list()
+            .map(|(a, b)| format!("    <edge source=\"{a}\" target=\"{b}\"/>"));
+
+        [
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">".to_string(),
+            "  <graph id=\"lan\" edgedefault=\"undirected\">".to_string(),
+        ]
+        .into_iter()
+        .chain(nodes)
+        .chain(edges)
+        .chain(["  </graph>".to_string(), "</graphml>".to_string()])
+        .join("\n")
+    }
+
+    fn names_of(&self, nodes: &[usize]) -> String {
+        nodes.iter().map(|&v| &self.names[v]).sorted().join(",")
     }
 }
 
 pub fn part1(input: &Graph) -> String {
-    triplets(input)
+    input
+        .triangles()
         .iter()
-        .filter(|triplet| triplet.iter().any(|name| name.starts_with('t')))
+        .filter(|triangle| triangle.iter().any(|&v| input.names[v].starts_with('t')))
         .count()
         .to_string()
 }
 
 pub fn part2(input: &Graph) -> String {
-    max_clique(
-        input,
-        HashSet::new(),
-        input.keys().cloned().collect(),
-        HashSet::new(),
-    )
-    .iter()
-    .sorted()
-    .join(",")
+    input
+        .maximal_cliques()
+        .iter()
+        .max_by_key(|clique| clique.len())
+        .map(|clique| input.names_of(clique))
+        .unwrap()
+}
+
+pub fn debug(input: &Graph, args: &[String]) -> String {
+    match args[0].as_str() {
+        "cliques" if args.len() < 2 => "usage: cliques <size>".to_string(),
+        "cliques" => {
+            let size: usize = args[1].parse().expect("Clique size must be a number");
+            input
+                .maximal_cliques()
+                .iter()
+                .filter(|clique| clique.len() == size)
+                .map(|clique| input.names_of(clique))
+                .sorted()
+                .join("\n")
+        }
+        "components" => input
+            .components()
+            .iter()
+            .map(|component| format!("{}: {}", component.len(), input.names_of(component)))
+            .join("\n"),
+        "dot" => input.to_dot(),
+        "graphml" => input.to_graphml(),
+        mode => format!("unknown mode {mode}, expected cliques, components, dot or graphml"),
+    }
 }
 
 #[test]
@@ -103,4 +233,14 @@ fn test_day23() {
     let input = parse("ka-co\nta-co\nde-co\nta-ka\nde-ta\nka-de");
     assert_eq!(part1(&input), "3");
     assert_eq!(part2(&input), "co,de,ka,ta");
+
+    let input = parse("a-b\nb-c\nc-a\nc-d\nx-y");
+    let cliques = input
+        .maximal_cliques()
+        .iter()
+        .map(|c| input.names_of(c))
+        .sorted()
+        .collect_vec();
+    assert_eq!(cliques, ["a,b,c", "c,d", "x,y"]);
+    assert_eq!(input.components().len(), 2);
 }
@@ -1,5 +1,5 @@
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 enum Gate {
     And(String, String),
@@ -7,11 +7,43 @@ enum Gate {
     Xor(String, String),
 }
 
+impl Gate {
+    fn inputs(&self) -> (&str, &str) {
+        match self {
+            Gate::And(l, r) | Gate::Or(l, r) | Gate::Xor(l, r) => (l, r),
+        }
+    }
+
+    fn apply(&self, l: bool, r: bool) -> bool {
+        match self {
+            Gate::And(_, _) => l & r,
+            Gate::Or(_, _) => l | r,
+            Gate::Xor(_, _) => l ^ r,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Gate::And(_, _) => "AND",
+            Gate::Or(_, _) => "OR",
+            Gate::Xor(_, _) => "XOR",
+        }
+    }
+}
+
 pub struct Circuit {
     wires: HashMap<String, bool>,
     gates: HashMap<String, Gate>,
 }
 
+struct Netlist<'a> {
+    wires: Vec<&'a str>,
+    steps: Vec<(usize, usize, usize, &'a Gate)>,
+    x: Vec<usize>,
+    y: Vec<usize>,
+    z: Vec<usize>,
+}
+
 pub fn parse(input: &str) -> Circuit {
     let (initial, gates) = input.split("\n\n").collect_tuple().unwrap();
 
@@ -45,85 +77,308 @@ fn wire_type(wire: &str) -> Option<(char, usize)> {
     Some((wire.chars().next()?, wire.get(1..)?.parse::<usize>().ok()?))
 }
 
-pub fn part1(input: &Circuit) -> String {
-    let mut wires = input.wires.clone();
-
-    while let Some((output, value)) = input
-        .gates
-        .iter()
-        .filter(|(out, _)| !wires.contains_key(*out))
-        .find_map(|(out, gate)| {
-            let (l, r) = match gate {
-                Gate::And(l, r) | Gate::Or(l, r) | Gate::Xor(l, r) => (l, r),
-            };
-            wires.get(l).zip(wires.get(r)).map(|(&l_val, &r_val)| {
-                let val = match gate {
-                    Gate::And(_, _) => l_val & r_val,
-                    Gate::Or(_, _) => l_val | r_val,
-                    Gate::Xor(_, _) => l_val ^ r_val,
-                };
-                (out.clone(), val)
-            })
-        })
-    {
-        wires.insert(output, value);
-    }
+// a mask of the lowest n bits, all of them once n reaches the width
+fn low_bits(n: usize) -> u128 {
+    1u128.checked_shl(n as u32).map_or(u128::MAX, |bit| bit - 1)
+}
 
-    (0..)
-        .take_while(|i| wires.contains_key(&format!("z{:02}", i)))
-        .map(|i| (wires[&format!("z{:02}", i)] as u64) << i)
-        .sum::<u64>()
-        .to_string()
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
 }
 
-pub fn part2(input: &Circuit) -> String {
-    let (min_bit, max_bit) = input
-        .wires
-        .keys()
-        .filter_map(|w| w[1..].parse::<usize>().ok())
-        .fold((usize::MAX, 0), |(min, max), n| {
-            (min.min(n), max.max(n + 1))
+impl Circuit {
+    // kahn's algorithm over the gates, with the outputs of each swapped pair exchanged;
+    // returns none when the swaps introduce a cycle
+    fn netlist<'a>(&'a self, swaps: &[(&'a str, &'a str)]) -> Option<Netlist<'a>> {
+        let output = |out: &'a str| {
+            swaps
+                .iter()
+                .find_map(|&(a, b)| match out {
+                    o if o == a => Some(b),
+                    o if o == b => Some(a),
+                    _ => None,
+                })
+                .unwrap_or(out)
+        };
+
+        let wires: Vec<&str> = self
+            .wires
+            .keys()
+            .map(String::as_str)
+            .chain(self.gates.iter().flat_map(|(out, gate)| {
+                let (l, r) = gate.inputs();
+                [out.as_str(), l, r]
+            }))
+            .unique()
+            .sorted()
+            .collect();
+        let index: HashMap<&str, usize> = wires.iter().enumerate().map(|(i, &w)| (w, i)).collect();
+
+        let gates = self
+            .gates
+            .iter()
+            .map(|(out, gate)| {
+                let (l, r) = gate.inputs();
+                (index[l], index[r], index[output(out.as_str())], gate)
+            })
+            .collect_vec();
+
+        let mut consumers = vec![vec![]; wires.len()];
+        gates.iter().enumerate().for_each(|(g, &(l, r, _, _))| {
+            consumers[l].push(g);
+            consumers[r].push(g);
         });
 
-    let xy_wire = |w: &str| wire_type(w).map_or(false, |(t, p)| "xy".contains(t) && p > min_bit);
+        let driven: HashSet<usize> = gates.iter().map(|&(_, _, out, _)| out).collect();
+        let mut ready = (0..wires.len())
+            .filter(|w| !driven.contains(w))
+            .collect_vec();
+        let mut pending = vec![2; gates.len()];
+        let mut steps = Vec::with_capacity(gates.len());
 
-    let used_in_gates = |output: &str, gate_type: fn(&Gate) -> bool| {
-        input.gates.values().any(|g| match g {
-            g if gate_type(g) => match g {
-                Gate::And(a, b) | Gate::Or(a, b) | Gate::Xor(a, b) => a == output || b == output,
-            },
-            _ => false,
+        while let Some(wire) = ready.pop() {
+            for &g in &consumers[wire] {
+                pending[g] -= 1;
+                if pending[g] == 0 {
+                    steps.push(gates[g]);
+                    ready.push(gates[g].2);
+                }
+            }
+        }
+
+        let bits = |prefix: char| {
+            (0..)
+                .map_while(|i| index.get(format!("{prefix}{i:02}").as_str()).copied())
+                .collect_vec()
+        };
+
+        (steps.len() == gates.len()).then(|| Netlist {
+            x: bits('x'),
+            y: bits('y'),
+            z: bits('z'),
+            wires,
+            steps,
         })
-    };
-
-    input
-        .gates
-        .iter()
-        .filter(|(out, gate)| {
-            let (l, r) = match gate {
-                Gate::And(l, r) | Gate::Or(l, r) | Gate::Xor(l, r) => (l, r),
-            };
+    }
 
-            match wire_type(out) {
-                Some(('z', pos)) if pos < max_bit => !matches!(gate, Gate::Xor(_, _)),
-                _ => {
-                    let both_xy = xy_wire(l) && xy_wire(r);
-                    match gate {
-                        Gate::Xor(_, _) if !both_xy => true,
-                        Gate::Xor(_, _) if both_xy => {
-                            !used_in_gates(out, |g| matches!(g, Gate::Xor(_, _)))
-                        }
-                        Gate::And(_, _) if both_xy => {
-                            !used_in_gates(out, |g| matches!(g, Gate::Or(_, _)))
+    fn to_dot(&self, faulty: &HashSet<&str>) -> String {
+        let wire = |w: &str| match faulty.contains(w) {
+            true => format!("  \"{w}\" [style=filled, fillcolor=red];"),
+            false => format!("  \"{w}\";"),
+        };
+
+        let gates = self
+            .gates
+            .iter()
+            .sorted_by_key(|(out, _)| *out)
+            .flat_map(|(out, gate)| {
+                let (l, r) = gate.inputs();
+                [
+                    format!(
+                        "  \"{}_{out}\" [label={}, shape=box];",
+                        gate.name(),
+                        gate.name()
+                    ),
+                    format!("  \"{l}\" -> \"{}_{out}\";", gate.name()),
+                    format!("  \"{r}\" -> \"{}_{out}\";", gate.name()),
+                    format!("  \"{}_{out}\" -> \"{out}\";", gate.name()),
+                ]
+            });
+
+        self.wires
+            .keys()
+            .chain(self.gates.keys())
+            .sorted()
+            .map(|w| wire(w))
+            .chain(gates)
+            .fold("digraph circuit {\n".to_string(), |dot, line| {
+                dot + &line + "\n"
+            })
+            + "}"
+    }
+
+    // candidate miswired outputs, found by checking the structure of a ripple-carry adder
+    fn suspicious_wires(&self) -> Vec<&str> {
+        let (min_bit, max_bit) = self
+            .wires
+            .keys()
+            .filter_map(|w| w[1..].parse::<usize>().ok())
+            .fold((usize::MAX, 0), |(min, max), n| {
+                (min.min(n), max.max(n + 1))
+            });
+
+        let xy_wire = |w: &str| wire_type(w).is_some_and(|(t, p)| "xy".contains(t) && p > min_bit);
+
+        let used_in_gates = |output: &str, gate_type: fn(&Gate) -> bool| {
+            self.gates.values().any(|g| {
+                let (a, b) = g.inputs();
+                gate_type(g) && (a == output || b == output)
+            })
+        };
+
+        self.gates
+            .iter()
+            .filter(|(out, gate)| {
+                let (l, r) = gate.inputs();
+
+                match wire_type(out) {
+                    Some(('z', pos)) if pos < max_bit => !matches!(gate, Gate::Xor(_, _)),
+                    _ => {
+                        let both_xy = xy_wire(l) && xy_wire(r);
+                        match gate {
+                            Gate::Xor(_, _) if !both_xy => true,
+                            Gate::Xor(_, _) if both_xy => {
+                                !used_in_gates(out, |g| matches!(g, Gate::Xor(_, _)))
+                            }
+                            Gate::And(_, _) if both_xy => {
+                                !used_in_gates(out, |g| matches!(g, Gate::Or(_, _)))
+                            }
+                            _ => false,
                         }
-                        _ => false,
                     }
                 }
-            }
-        })
-        .map(|(out, _)| out)
-        .sorted()
-        .join(",")
+            })
+            .map(|(out, _)| out.as_str())
+            .sorted()
+            .collect()
+    }
+
+    // tries every pairing of the candidates until one turns the circuit into a working adder
+    fn find_swaps<'a>(&'a self, candidates: &[&'a str]) -> Option<Vec<(&'a str, &'a str)>> {
+        fn pairings<'a>(
+            circuit: &'a Circuit,
+            rest: &[&'a str],
+            swaps: &mut Vec<(&'a str, &'a str)>,
+        ) -> bool {
+            let Some((&first, rest)) = rest.split_first() else {
+                return circuit.netlist(swaps).is_some_and(|n| n.verify().is_ok());
+            };
+
+            (0..rest.len()).any(|i| {
+                swaps.push((first, rest[i]));
+                let others = rest
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, &w)| w);
+                let fixed = pairings(circuit, &others.collect_vec(), swaps);
+                if !fixed {
+                    swaps.pop();
+                }
+                fixed
+            })
+        }
+
+        let mut swaps = vec![];
+        (candidates.len().is_multiple_of(2) && pairings(self, candidates, &mut swaps))
+            .then_some(swaps)
+    }
+}
+
+impl Netlist<'_> {
+    fn run(&self, values: &mut [bool]) {
+        self.steps
+            .iter()
+            .for_each(|&(l, r, out, gate)| values[out] = gate.apply(values[l], values[r]));
+    }
+
+    fn evaluate(&self, inputs: &HashMap<String, bool>) -> Vec<bool> {
+        let mut values: Vec<bool> = self
+            .wires
+            .iter()
+            .map(|&w| inputs.get(w) == Some(&true))
+            .collect();
+        self.run(&mut values);
+        values
+    }
+
+    fn output(&self, values: &[bool]) -> u128 {
+        self.z
+            .iter()
+            .enumerate()
+            .map(|(i, &w)| (values[w] as u128) << i)
+            .sum()
+    }
+
+    fn add(&self, x: u64, y: u64) -> u128 {
+        let mut values = vec![false; self.wires.len()];
+        [(&self.x, x), (&self.y, y)].iter().for_each(|(bits, n)| {
+            bits.iter()
+                .enumerate()
+                .for_each(|(i, &w)| values[w] = n.checked_shr(i as u32).is_some_and(|n| n & 1 == 1))
+        });
+        self.run(&mut values);
+        self.output(&values)
+    }
+
+    // checks z = x + y, returning the lowest z bit that came out wrong
+    fn verify(&self) -> Result<(), usize> {
+        let bits = self.x.len().min(self.y.len());
+        let mask = |n: u64| n & low_bits(bits) as u64;
+        let z_mask = low_bits(self.z.len());
+
+        let exhaustive = (bits <= 8)
+            .then(|| (0..1u64 << bits).cartesian_product(0..1u64 << bits))
+            .into_iter()
+            .flatten();
+
+        let local = (0..bits).flat_map(|i| {
+            let shift = i.saturating_sub(1);
+            (0..4u64)
+                .cartesian_product(0..4u64)
+                .map(move |(x, y)| (mask(x << shift), mask(y << shift)))
+        });
+
+        let mut seed = 0x2024_1224;
+        let random = (0..64).map(|_| (mask(xorshift(&mut seed)), mask(xorshift(&mut seed))));
+
+        exhaustive
+            .chain(local)
+            .chain(random)
+            .map(|(x, y)| (self.add(x, y) ^ (x as u128 + y as u128)) & z_mask)
+            .find(|&wrong| wrong != 0)
+            .map_or(Ok(()), |wrong| Err(wrong.trailing_zeros() as usize))
+    }
+}
+
+pub fn part1(input: &Circuit) -> String {
+    let netlist = input.netlist(&[]).unwrap();
+    netlist.output(&netlist.evaluate(&input.wires)).to_string()
+}
+
+pub fn part2(input: &Circuit) -> String {
+    let candidates = input.suspicious_wires();
+
+    match input.find_swaps(&candidates) {
+        Some(swaps) => swaps.iter().flat_map(|&(a, b)| [a, b]).sorted().join(","),
+        None => format!("no pairing of {} fixes the adder", candidates.join(",")),
+    }
+}
+
+pub fn debug(input: &Circuit, args: &[String]) -> String {
+    let candidates = input.suspicious_wires();
+
+    match args[0].as_str() {
+        "dot" => input.to_dot(&candidates.into_iter().collect()),
+        "verify" => match input.netlist(&[]).map(|n| n.verify()) {
+            None => "circuit contains a cycle".to_string(),
+            Some(Ok(())) => "circuit is a working adder".to_string(),
+            Some(Err(bit)) => match input.find_swaps(&candidates) {
+                Some(swaps) => format!(
+                    "first wrong bit: z{bit:02}\nfixed by swapping: {}",
+                    swaps.iter().map(|(a, b)| format!("{a}<->{b}")).join(", ")
+                ),
+                None => format!(
+                    "first wrong bit: z{bit:02}\nno pairing of {} fixes the adder",
+                    candidates.join(",")
+                ),
+            },
+        },
+        mode => format!("unknown mode {mode}, expected dot or verify"),
+    }
 }
 
 #[test]
@@ -141,5 +396,29 @@ fn test_day24() {
          x02 OR y02 -> z02",
     );
     assert_eq!(part1(&input), "4");
-    assert_eq!(part2(&input), "z00,z02");
+    // not an adder at all, so no pairing of swaps can fix it
+    assert_eq!(part2(&input), "no pairing of z00,z02 fixes the adder");
+
+    let adder = parse(
+        "x00: 0
+         x01: 0
+         y00: 0
+         y01: 0
+
+         x00 XOR y00 -> z01
+         x00 AND y00 -> c00
+         x01 XOR y01 -> s01
+         x01 AND y01 -> c01
+         s01 XOR c00 -> z00
+         s01 AND c00 -> d01
+         c01 OR d01 -> z02",
+    );
+    assert_eq!(adder.netlist(&[]).unwrap().verify(), Err(0));
+    assert_eq!(low_bits(64), u64::MAX as u128);
+    assert_eq!(low_bits(130), u128::MAX);
+    assert_eq!(
+        adder.find_swaps(&["z00", "z01"]),
+        Some(vec![("z00", "z01")])
+    );
+    assert!(adder.netlist(&[("z00", "c00")]).is_none());
 }
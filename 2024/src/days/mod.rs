@@ -1,4 +1,5 @@
 type DaySolution = (fn(&str) -> String, fn(&str) -> String);
+type DayDebug = fn(&str, &[String]) -> String;
 
 macro_rules! make_day {
     ($($day:ident),*) => {
@@ -19,3 +20,21 @@ make_day!(
     day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12, day13,
     day14, day15, day16, day17, day18, day19, day20, day21, day22, day23, day24, day25
 );
+
+macro_rules! make_debug {
+    ($($num:literal => $day:ident),*) => {
+        const DEBUG: &[(usize, DayDebug)] = &[
+            $(($num, |input, args| $day::debug(&$day::parse(input), args)),)*
+        ];
+
+        pub fn debug(day: usize) -> Option<DayDebug> {
+            DEBUG.iter().find(|(num, _)| *num == day).map(|&(_, debug)| debug)
+        }
+    }
+}
+
+make_debug!(
+    5 => day05, 6 => day06, 7 => day07, 9 => day09, 12 => day12, 14 => day14, 15 => day15,
+    16 => day16, 18 => day18, 19 => day19, 20 => day20, 21 => day21, 22 => day22, 23 => day23,
+    24 => day24
+);
@@ -1,9 +1,9 @@
-pub fn parse(input: &str) -> Vec<i32> {
+pub fn parse(input: &str) -> Vec<i64> {
     input
         .lines()
         .map(|line| {
             let (dir, dist) = line.split_at(1);
-            let dist: i32 = dist.parse().unwrap();
+            let dist: i64 = dist.parse().unwrap();
             match dir {
                 "L" => -dist,
                 "R" => dist,
@@ -13,33 +13,80 @@ pub fn parse(input: &str) -> Vec<i32> {
         .collect()
 }
 
-fn normalize(pos: i32) -> i32 {
-    pos.rem_euclid(100)
+// a dial numbered 0 to size - 1 that wraps around, pointing at start before any rotation
+struct Dial {
+    size: i64,
+    start: i64,
 }
 
-pub fn part1(input: &[i32]) -> String {
-    input
-        .iter()
-        .fold((50i32, 0u32), |(pos, count), &rotation| {
-            let new_pos = normalize(pos + rotation);
-            (new_pos, count + (new_pos == 0) as u32)
+const SAFE: Dial = Dial {
+    size: 100,
+    start: 50,
+};
+
+impl Dial {
+    // the dial position without wrapping after each rotation, it only wraps when read
+    fn unwrapped<'a>(&self, rotations: &'a [i64]) -> impl Iterator<Item = (i128, i64)> + 'a {
+        let start = self.start.rem_euclid(self.size) as i128;
+        rotations.iter().scan(start, |pos, &rotation| {
+            let before = *pos;
+            *pos += rotation as i128;
+            Some((before, rotation))
         })
-        .1
-        .to_string()
+    }
+
+    fn zeros_landed(&self, rotations: &[i64]) -> u64 {
+        self.unwrapped(rotations)
+            .filter(|&(pos, rotation)| (pos + rotation as i128) % self.size as i128 == 0)
+            .count() as u64
+    }
+
+    // every click that lands on a multiple of the size is a zero, so the clicks from pos to
+    // pos + rotation pass one per multiple in between, excluding where it starts from
+    fn zeros_passed(&self, rotations: &[i64]) -> u64 {
+        let size = self.size as i128;
+        self.unwrapped(rotations)
+            .map(|(pos, rotation)| {
+                let end = pos + rotation as i128;
+                match rotation > 0 {
+                    true => end.div_euclid(size) - pos.div_euclid(size),
+                    false => (pos - 1).div_euclid(size) - (end - 1).div_euclid(size),
+                }
+            })
+            .sum::<i128>() as u64
+    }
 }
 
-pub fn part2(input: &[i32]) -> String {
-    input
-        .iter()
-        .fold((50i32, 0u32), |(start_pos, count), &rotation| {
-            let zeros = (1..=rotation.abs() as u32)
-                .map(|click| normalize(start_pos + rotation.signum() * click as i32))
-                .filter(|&pos| pos == 0)
-                .count() as u32;
-            (normalize(start_pos + rotation), count + zeros)
-        })
-        .1
-        .to_string()
+pub fn part1(input: &[i64]) -> String {
+    SAFE.zeros_landed(input).to_string()
+}
+
+pub fn part2(input: &[i64]) -> String {
+    SAFE.zeros_passed(input).to_string()
+}
+
+pub fn debug(input: &[i64], args: &[String]) -> String {
+    match args {
+        [mode, size, rest @ ..] if mode == "dial" => {
+            let number = |n: &String| n.parse().expect("Dial settings must be numbers");
+            let dial = Dial {
+                size: number(size),
+                start: rest.first().map_or(SAFE.start, number),
+            };
+            if dial.size <= 0 {
+                return format!("dial size must be positive, got {}", dial.size);
+            }
+
+            format!(
+                "dial of {} starting at {}: landed on 0 {} times, passed 0 {} times",
+                dial.size,
+                dial.start,
+                dial.zeros_landed(input),
+                dial.zeros_passed(input)
+            )
+        }
+        _ => "usage: dial <size> [start]".to_string(),
+    }
 }
 
 #[test]
@@ -47,4 +94,20 @@ fn test_day01() {
     let input = parse("L68\nL30\nR48\nL5\nR60\nL55\nL1\nL99\nR14\nL82");
     assert_eq!(part1(&input), "3");
     assert_eq!(part2(&input), "6");
+
+    let input = parse("R1000000000\nL1000000050\nR50");
+    assert_eq!(part2(&input), "20000001");
+
+    let dial = Dial { size: 10, start: 0 };
+    let input = parse("L10\nR25\nL5\nL1");
+    assert_eq!(dial.zeros_landed(&input), 2);
+    assert_eq!(dial.zeros_passed(&input), 4);
+
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    assert_eq!(debug(&input, &args(&["10"])), "usage: dial <size> [start]");
+    assert_eq!(
+        debug(&input, &args(&["dial", "0"])),
+        "dial size must be positive, got 0"
+    );
+    assert!(debug(&input, &args(&["dial", "10"])).ends_with("passed 0 4 times"));
 }
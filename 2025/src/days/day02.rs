@@ -1,4 +1,3 @@
-use rayon::prelude::*;
 use std::ops::RangeInclusive;
 
 pub fn parse(input: &str) -> Vec<RangeInclusive<u64>> {
@@ -12,35 +11,77 @@ pub fn parse(input: &str) -> Vec<RangeInclusive<u64>> {
         .collect()
 }
 
-fn has_repetition(id_str: &str, k: usize) -> bool {
-    let len = id_str.len();
-    len % k == 0 && {
-        let part_len = len / k;
-        let first_part = &id_str[0..part_len];
-        (1..k).all(|i| &id_str[i * part_len..(i + 1) * part_len] == first_part)
+// the number a block of `len` digits is multiplied by to repeat it `k` times
+fn repeater(len: u32, k: u32) -> u128 {
+    (0..k).map(|i| 10u128.pow(i * len)).sum()
+}
+
+// sum of every id with `digits` digits in the range made of one block repeated `k` times,
+// these are block * repeater for every block of the right length, so it's an arithmetic series
+fn repeated_sum(range: &RangeInclusive<u64>, digits: u32, k: u32) -> u128 {
+    let len = digits / k;
+    let m = repeater(len, k);
+    let (start, end) = (*range.start() as u128, *range.end() as u128);
+
+    let lo = start.div_ceil(m).max(10u128.pow(len - 1));
+    let hi = (end / m).min(10u128.pow(len) - 1);
+    if lo > hi {
+        return 0;
     }
+    (lo + hi) * (hi - lo + 1) / 2 * m
+}
+
+fn prime_factors(n: u32) -> Vec<u32> {
+    (2..=n)
+        .filter(|&p| n.is_multiple_of(p) && (2..p).all(|q| !p.is_multiple_of(q)))
+        .collect()
 }
 
-fn invalid_id(id: u64, min_repetitions: usize) -> bool {
-    let id_str = id.to_string();
-    (min_repetitions..=id_str.len()).any(|k| has_repetition(&id_str, k))
+// repeating k times is also repeating p times for any prime p | k, so the invalid ids are
+// the union over the prime factors of the digit count, counted once by inclusion-exclusion
+fn invalid_sum(range: &RangeInclusive<u64>, any_repetitions: bool) -> u128 {
+    let max_digits = range.end().checked_ilog10().unwrap_or(0) + 1;
+
+    (2..=max_digits)
+        .map(|digits| {
+            if !any_repetitions {
+                return match digits % 2 {
+                    0 => repeated_sum(range, digits, 2),
+                    _ => 0,
+                };
+            }
+
+            let primes = prime_factors(digits);
+            (1..1u32 << primes.len())
+                .map(|subset| {
+                    let k = (0..primes.len())
+                        .filter(|i| subset >> i & 1 == 1)
+                        .map(|i| primes[i])
+                        .product();
+                    let sum = repeated_sum(range, digits, k) as i128;
+                    match subset.count_ones() % 2 {
+                        1 => sum,
+                        _ => -sum,
+                    }
+                })
+                .sum::<i128>() as u128
+        })
+        .sum()
 }
 
 pub fn part1(input: &[RangeInclusive<u64>]) -> String {
     input
-        .par_iter()
-        .flat_map(|range| range.clone())
-        .filter(|&id| has_repetition(&id.to_string(), 2))
-        .sum::<u64>()
+        .iter()
+        .map(|range| invalid_sum(range, false))
+        .sum::<u128>()
         .to_string()
 }
 
 pub fn part2(input: &[RangeInclusive<u64>]) -> String {
     input
-        .par_iter()
-        .flat_map(|range| range.clone())
-        .filter(|&id| invalid_id(id, 2))
-        .sum::<u64>()
+        .iter()
+        .map(|range| invalid_sum(range, true))
+        .sum::<u128>()
         .to_string()
 }
 
@@ -49,4 +90,8 @@ fn test_day02() {
     let input = parse("11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124");
     assert_eq!(part1(&input), "1227775554");
     assert_eq!(part2(&input), "4174379265");
+
+    let input = parse("1-18446744073709551615");
+    assert_eq!(part1(&input), "12509613850169742155792778978");
+    assert_eq!(part2(&input), "12510120345498176585131248687");
 }
@@ -1,4 +1,5 @@
 use itertools::Itertools;
+use std::fmt;
 
 pub fn parse(input: &str) -> Vec<Vec<u32>> {
     input
@@ -13,48 +14,102 @@ pub fn parse(input: &str) -> Vec<Vec<u32>> {
         .collect()
 }
 
-fn max_joltage(bank: &[u32]) -> u32 {
-    (0..bank.len())
-        .tuple_combinations()
-        .map(|(i, j)| bank[i] * 10 + bank[j])
-        .max()
-        .unwrap_or(0)
+// the batteries turned on in a bank, in order. their digits are the joltage, which can be
+// longer than any integer type holds
+struct Joltage {
+    indices: Vec<usize>,
+    digits: Vec<u32>,
 }
 
-pub fn part1(input: &[Vec<u32>]) -> String {
-    input
-        .iter()
-        .map(|bank| max_joltage(bank))
-        .sum::<u32>()
-        .to_string()
+impl fmt::Display for Joltage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.digits.iter().try_for_each(|d| write!(f, "{d}"))
+    }
 }
 
-fn max_joltage_12(bank: &[u32]) -> u64 {
-    let batteries = 12;
+// monotonic stack: a battery knocks out smaller ones before it while there are still
+// batteries we can afford to leave off. a bank with fewer than k batteries has no answer
+fn max_joltage(bank: &[u32], k: usize) -> Option<Joltage> {
+    if k > bank.len() {
+        return None;
+    }
+    let mut skips = bank.len() - k;
+    let mut stack: Vec<usize> = Vec::with_capacity(bank.len());
 
-    (0..batteries)
-        .scan(-1i32, |last_idx, pos| {
-            let needed = batteries - pos - 1;
-            let start = (*last_idx + 1) as usize;
-            let end = bank.len() - needed;
+    for (i, &digit) in bank.iter().enumerate() {
+        while skips > 0 && stack.last().is_some_and(|&top| bank[top] < digit) {
+            stack.pop();
+            skips -= 1;
+        }
+        stack.push(i);
+    }
+    stack.truncate(k);
 
-            let (max_digit, max_idx) = (start..end)
-                .map(|i| (bank[i], i))
-                .max_by_key(|&(d, i)| (d, -(i as i32)))
-                .unwrap();
+    Some(Joltage {
+        digits: stack.iter().map(|&i| bank[i]).collect(),
+        indices: stack,
+    })
+}
 
-            *last_idx = max_idx as i32;
-            Some(max_digit)
-        })
-        .fold(0u64, |acc, d| acc * 10 + d as u64)
+// adds a big endian number onto a little endian running total
+fn add_digits(total: &mut Vec<u32>, digits: &[u32]) {
+    let mut carry = 0;
+    for i in 0..total.len().max(digits.len()) {
+        if i == total.len() {
+            total.push(0);
+        }
+        let digit = digits.len().checked_sub(i + 1).map_or(0, |j| digits[j]);
+        let sum = total[i] + digit + carry;
+        (total[i], carry) = (sum % 10, sum / 10);
+    }
+    if carry > 0 {
+        total.push(carry);
+    }
+}
+
+// summed digit by digit so any k works, banks too short for k add nothing
+fn total_joltage(input: &[Vec<u32>], k: usize) -> String {
+    let mut total = vec![];
+    input
+        .iter()
+        .filter_map(|bank| max_joltage(bank, k))
+        .for_each(|joltage| add_digits(&mut total, &joltage.digits));
+
+    while total.len() > 1 && total.last() == Some(&0) {
+        total.pop();
+    }
+    match total.is_empty() {
+        true => "0".to_string(),
+        false => total.iter().rev().join(""),
+    }
+}
+
+pub fn part1(input: &[Vec<u32>]) -> String {
+    total_joltage(input, 2)
 }
 
 pub fn part2(input: &[Vec<u32>]) -> String {
+    total_joltage(input, 12)
+}
+
+pub fn debug(input: &[Vec<u32>], args: &[String]) -> String {
+    let k = args[0].parse().expect("Battery count must be a number");
+
     input
         .iter()
-        .map(|bank| max_joltage_12(bank))
-        .sum::<u64>()
-        .to_string()
+        .map(|bank| {
+            let Some(joltage) = max_joltage(bank, k) else {
+                return format!("{}\nfewer than {k} batteries", bank.iter().join(""));
+            };
+            let marks: String = (0..bank.len())
+                .map(|i| match joltage.indices.contains(&i) {
+                    true => '^',
+                    false => ' ',
+                })
+                .collect();
+            format!("{}\n{marks} {joltage}", bank.iter().join(""))
+        })
+        .join("\n")
 }
 
 #[test]
@@ -62,4 +117,18 @@ fn test_day03() {
     let input = parse("987654321111111\n811111111111119\n234234234234278\n818181911112111");
     assert_eq!(part1(&input), "357");
     assert_eq!(part2(&input), "3121910778619");
+
+    let joltage = max_joltage(&input[2], 12).unwrap();
+    assert_eq!(joltage.to_string(), "434234234278");
+    assert_eq!(joltage.indices, [2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+    assert_eq!(max_joltage(&input[3], 1).unwrap().indices, [6]);
+    assert!(max_joltage(&input[3], 16).is_none());
+
+    let short = parse("7\n45");
+    assert_eq!(part1(&short), "45");
+
+    let long = parse(&["9".repeat(40), "1".repeat(40)].join("\n"));
+    let joltage = max_joltage(&long[0], 39).unwrap();
+    assert_eq!(joltage.to_string(), "9".repeat(39));
+    assert_eq!(total_joltage(&long, 39), format!("1{}0", "1".repeat(38)));
 }
@@ -1,5 +1,4 @@
-use itertools::iproduct;
-use std::collections::HashSet;
+use itertools::{iproduct, Itertools};
 
 pub fn parse(input: &str) -> Vec<Vec<char>> {
     input
@@ -9,63 +8,101 @@ pub fn parse(input: &str) -> Vec<Vec<char>> {
         .collect()
 }
 
+fn neighbors(rows: usize, cols: usize, i: usize, j: usize) -> impl Iterator<Item = (usize, usize)> {
+    iproduct!(-1..=1, -1..=1)
+        .filter(|&(di, dj)| di != 0 || dj != 0)
+        .map(move |(di, dj)| (i as i32 + di, j as i32 + dj))
+        .filter(move |&(ni, nj)| ni >= 0 && ni < rows as i32 && nj >= 0 && nj < cols as i32)
+        .map(|(ni, nj)| (ni as usize, nj as usize))
+}
+
 fn count_neighbors(grid: &[Vec<char>], i: usize, j: usize) -> usize {
     let rows = grid.len();
     let cols = if rows > 0 { grid[0].len() } else { 0 };
 
-    iproduct!(-1..=1, -1..=1)
-        .filter(|&(di, dj)| di != 0 || dj != 0)
-        .map(|(di, dj)| (i as i32 + di, j as i32 + dj))
-        .filter(|&(ni, nj)| ni >= 0 && ni < rows as i32 && nj >= 0 && nj < cols as i32)
-        .filter(|&(ni, nj)| grid[ni as usize][nj as usize] == '@')
+    neighbors(rows, cols, i, j)
+        .filter(|&(ni, nj)| grid[ni][nj] == '@')
         .count()
 }
 
-fn find_isolated(grid: &[Vec<char>]) -> HashSet<(usize, usize)> {
+// every roll removed, grouped by the wave it went in, the first wave being the rolls that
+// are accessible from the start
+fn removal_waves(grid: &[Vec<char>]) -> Vec<Vec<(usize, usize)>> {
     let rows = grid.len();
     let cols = if rows > 0 { grid[0].len() } else { 0 };
 
-    iproduct!(0..rows, 0..cols)
-        .filter(move |&(i, j)| grid[i][j] == '@')
-        .filter(move |&(i, j)| count_neighbors(grid, i, j) < 4)
-        .collect()
+    let mut counts = vec![vec![0; cols]; rows];
+    let mut removed = vec![vec![false; cols]; rows];
+    let mut wave: Vec<_> = iproduct!(0..rows, 0..cols)
+        .filter(|&(i, j)| grid[i][j] == '@')
+        .filter(|&(i, j)| {
+            counts[i][j] = count_neighbors(grid, i, j);
+            counts[i][j] < 4
+        })
+        .collect();
+    wave.iter().for_each(|&(i, j)| removed[i][j] = true);
+
+    let mut waves = vec![];
+    while !wave.is_empty() {
+        let mut next = vec![];
+
+        // only the neighbors of removed rolls can drop below four
+        for &(i, j) in &wave {
+            for (ni, nj) in neighbors(rows, cols, i, j) {
+                if grid[ni][nj] != '@' || removed[ni][nj] {
+                    continue;
+                }
+                counts[ni][nj] -= 1;
+                if counts[ni][nj] < 4 {
+                    removed[ni][nj] = true;
+                    next.push((ni, nj));
+                }
+            }
+        }
+
+        waves.push(std::mem::replace(&mut wave, next));
+    }
+    waves
 }
 
-pub fn part1(input: &[Vec<char>]) -> String {
-    find_isolated(input).len().to_string()
+// removed rolls show their wave (0-9, then a-z, then '+'), rolls that stay are '@'
+fn heatmap(grid: &[Vec<char>], waves: &[Vec<(usize, usize)>]) -> String {
+    let mut map = grid.to_vec();
+    waves.iter().enumerate().for_each(|(wave, rolls)| {
+        let mark = char::from_digit(wave as u32, 36).unwrap_or('+');
+        rolls.iter().for_each(|&(i, j)| map[i][j] = mark);
+    });
+    map.iter()
+        .map(|row| row.iter().collect::<String>())
+        .join("\n")
 }
 
-fn remove_round(grid: &[Vec<char>]) -> (Vec<Vec<char>>, usize) {
-    let to_remove = find_isolated(grid);
+pub fn part1(input: &[Vec<char>]) -> String {
+    removal_waves(input).first().map_or(0, Vec::len).to_string()
+}
 
-    let new_grid = grid
+pub fn part2(input: &[Vec<char>]) -> String {
+    removal_waves(input)
         .iter()
-        .enumerate()
-        .map(|(i, row)| {
-            row.iter()
-                .enumerate()
-                // i hate if/else
-                .map(|(j, &cell)| to_remove.contains(&(i, j)).then_some('.').unwrap_or(cell))
-                .collect()
-        })
-        .collect::<Vec<_>>();
-
-    (new_grid, to_remove.len())
+        .map(Vec::len)
+        .sum::<usize>()
+        .to_string()
 }
 
-fn remove_all(grid: Vec<Vec<char>>, total_removed: usize) -> usize {
-    let (grid, removed) = remove_round(&grid);
+pub fn debug(input: &[Vec<char>], args: &[String]) -> String {
+    let waves = removal_waves(input);
 
-    match removed {
-        0 => total_removed,
-        _ => remove_all(grid, total_removed + removed),
+    match args[0].as_str() {
+        "heatmap" => heatmap(input, &waves),
+        "waves" => waves
+            .iter()
+            .enumerate()
+            .map(|(wave, rolls)| format!("wave {wave}: {} rolls {rolls:?}", rolls.len()))
+            .join("\n"),
+        mode => format!("unknown mode {mode}, expected heatmap or waves"),
     }
 }
 
-pub fn part2(input: &[Vec<char>]) -> String {
-    remove_all(input.to_vec(), 0).to_string()
-}
-
 #[test]
 fn test_day04() {
     let input = parse(
@@ -82,4 +119,11 @@ fn test_day04() {
     );
     assert_eq!(part1(&input), "13");
     assert_eq!(part2(&input), "43");
+
+    let waves = removal_waves(&input);
+    assert_eq!(
+        waves.iter().map(Vec::len).collect_vec(),
+        [13, 12, 7, 5, 2, 1, 1, 1, 1]
+    );
+    assert_eq!(heatmap(&input, &waves).lines().next(), Some("..00.0010."));
 }
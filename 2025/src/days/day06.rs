@@ -1,85 +1,192 @@
 use itertools::Itertools;
+use std::ops::Range;
 
-pub fn parse(input: &str) -> Vec<Vec<char>> {
-    input.lines().map(|line| line.chars().collect()).collect()
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
 }
 
-fn is_divider(grid: &[Vec<char>], col: usize) -> bool {
-    grid.iter().all(|row| row[col] == ' ')
+impl Op {
+    fn from_char(ch: char) -> Option<Op> {
+        match ch {
+            '+' => Some(Op::Add),
+            '-' => Some(Op::Subtract),
+            '*' => Some(Op::Multiply),
+            '/' => Some(Op::Divide),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Subtract => '-',
+            Op::Multiply => '*',
+            Op::Divide => '/',
+        }
+    }
+
+    fn apply(self, a: i128, b: i128) -> Option<i128> {
+        match self {
+            Op::Add => a.checked_add(b),
+            Op::Subtract => a.checked_sub(b),
+            Op::Multiply => a.checked_mul(b),
+            Op::Divide => a.checked_div(b),
+        }
+    }
 }
 
-fn find_ranges(grid: &[Vec<char>]) -> Vec<(usize, usize)> {
-    let cols = grid[0].len();
+struct Problem {
+    span: Range<usize>,
+    op: Op,
+    // the operand lines cut down to the span, padded with spaces where a line was short
+    lines: Vec<Vec<char>>,
+}
 
-    (0..=cols)
-        .scan(None, |start, col| {
-            (col < cols && !is_divider(grid, col))
-                .then(|| start.get_or_insert(col))
-                .and_then(|_| Some(None))
-                .or_else(|| start.take().map(|start| Some((start, col))))
-        })
-        .flatten()
-        .collect()
+impl Problem {
+    // each line is one number, read left to right
+    fn row_operands(&self) -> Vec<i128> {
+        self.lines
+            .iter()
+            .filter_map(|line| line.iter().collect::<String>().trim().parse().ok())
+            .collect()
+    }
+
+    // each column is one number read top to bottom, with the columns read right to left
+    fn column_operands(&self) -> Vec<i128> {
+        (0..self.span.len())
+            .rev()
+            .filter_map(|col| {
+                self.lines
+                    .iter()
+                    .map(|line| line[col])
+                    .filter(char::is_ascii_digit)
+                    .collect::<String>()
+                    .parse()
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn evaluate(&self, operands: &[i128]) -> Option<i128> {
+        let (&first, rest) = operands.split_first()?;
+        rest.iter()
+            .try_fold(first, |acc, &operand| self.op.apply(acc, operand))
+    }
+
+    fn show(&self, operands: &[i128]) -> String {
+        let expression = operands.iter().join(&format!(" {} ", self.op.symbol()));
+        match self.evaluate(operands) {
+            Some(result) => format!("{expression} = {result}"),
+            None => format!("{expression} = undefined"),
+        }
+    }
 }
 
-fn get_from_rows(grid: &[Vec<char>], start: usize, end: usize) -> Vec<u64> {
-    let rows = grid.len() - 1;
-
-    (0..rows)
-        .flat_map(|row| {
-            grid[row]
-                .iter()
-                .skip(start)
-                .take(end - start)
-                .copied()
-                .chunk_by(|&ch| ch.is_ascii_digit())
-                .into_iter()
-                .filter_map(|(_, group)| group.collect::<String>().parse().ok())
-                .collect::<Vec<u64>>()
-        })
-        .collect()
+pub struct Worksheet {
+    problems: Vec<Problem>,
 }
 
-fn get_from_cols(grid: &[Vec<char>], start: usize, end: usize) -> Vec<u64> {
-    (start..end)
-        .filter_map(|col| {
-            (0..grid.len() - 1)
-                .filter_map(|row| grid.get(row).and_then(|row| row.get(col)).copied())
-                .filter(|&ch| ch.is_ascii_digit())
-                .collect::<String>()
-                .parse()
-                .ok()
+impl Worksheet {
+    // the grand total, or the first problem that has no value (a division by zero, no
+    // operands, or a result too big for i128)
+    fn solve(&self, operands: fn(&Problem) -> Vec<i128>) -> Result<i128, &Problem> {
+        self.problems.iter().try_fold(0i128, |total, problem| {
+            problem
+                .evaluate(&operands(problem))
+                .and_then(|value| total.checked_add(value))
+                .ok_or(problem)
         })
-        .collect()
+    }
+
+    fn answer(&self, operands: fn(&Problem) -> Vec<i128>) -> String {
+        match self.solve(operands) {
+            Ok(total) => total.to_string(),
+            Err(problem) => format!(
+                "problem in columns {}..{} can't be solved: {}",
+                problem.span.start,
+                problem.span.end,
+                problem.show(&operands(problem))
+            ),
+        }
+    }
+
+    // every problem as it sits on the sheet, followed by how both readings evaluate it
+    fn pretty(&self) -> String {
+        self.problems
+            .iter()
+            .map(|problem| {
+                let block = problem
+                    .lines
+                    .iter()
+                    .map(|line| format!("  {}", line.iter().collect::<String>()))
+                    .join("\n");
+                format!(
+                    "columns {}..{}, {}\n{block}\n  rows: {}\n  cols: {}",
+                    problem.span.start,
+                    problem.span.end,
+                    problem.op.symbol(),
+                    problem.show(&problem.row_operands()),
+                    problem.show(&problem.column_operands()),
+                )
+            })
+            .join("\n\n")
+    }
 }
 
-fn solve(grid: &[Vec<char>], get_numbers: fn(&[Vec<char>], usize, usize) -> Vec<u64>) -> u64 {
-    find_ranges(grid)
+// problems are separated by columns that are blank on every line, lines are allowed to be
+// ragged so anything past the end of a line counts as blank
+pub fn parse(input: &str) -> Worksheet {
+    let lines = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect_vec();
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let grid = lines
+        .iter()
+        .map(|line| line.chars().pad_using(width, |_| ' ').collect_vec())
+        .collect_vec();
+
+    let Some((ops, operands)) = grid.split_last() else {
+        return Worksheet { problems: vec![] };
+    };
+
+    let problems = (0..width)
+        .chunk_by(|&col| grid.iter().all(|line| line[col] == ' '))
         .into_iter()
-        .filter_map(|(start, end)| {
-            let numbers = &get_numbers(grid, start, end);
-
-            grid.get(grid.len() - 1)?
-                .iter()
-                .skip(start)
-                .take(end - start)
-                .find(|&&ch| ch == '+' || ch == '*')
-                .copied()
-                .map(|op| match op {
-                    '+' => numbers.iter().sum(),
-                    '*' => numbers.iter().product(),
-                    _ => 0,
-                })
+        .filter(|(blank, _)| !blank)
+        .filter_map(|(_, cols)| {
+            let cols = cols.collect_vec();
+            let span = cols[0]..cols[cols.len() - 1] + 1;
+            let op = ops[span.clone()].iter().find_map(|&ch| Op::from_char(ch))?;
+
+            Some(Problem {
+                lines: operands
+                    .iter()
+                    .map(|line| line[span.clone()].to_vec())
+                    .collect(),
+                span,
+                op,
+            })
         })
-        .sum::<u64>()
+        .collect();
+
+    Worksheet { problems }
+}
+
+pub fn part1(input: &Worksheet) -> String {
+    input.answer(Problem::row_operands)
 }
 
-pub fn part1(input: &[Vec<char>]) -> String {
-    solve(input, get_from_rows).to_string()
+pub fn part2(input: &Worksheet) -> String {
+    input.answer(Problem::column_operands)
 }
 
-pub fn part2(input: &[Vec<char>]) -> String {
-    solve(input, get_from_cols).to_string()
+pub fn debug(input: &Worksheet, _args: &[String]) -> String {
+    input.pretty()
 }
 
 #[test]
@@ -87,4 +194,23 @@ fn test_day06() {
     let input = parse("123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n*   +   *   +  ");
     assert_eq!(part1(&input), "4277556");
     assert_eq!(part2(&input), "3263827");
+
+    let input = parse("100 8\n 20 4\n  5\n-   /");
+    assert_eq!(input.problems[0].row_operands(), [100, 20, 5]);
+    assert_eq!(input.problems[0].column_operands(), [5, 2, 1]);
+    assert_eq!(part1(&input), "77");
+    assert_eq!(part2(&input), "86");
+    assert!(input.pretty().contains("rows: 100 - 20 - 5 = 75"));
+
+    let input = parse("8 1\n0 2\n/ +");
+    assert_eq!(
+        part1(&input),
+        "problem in columns 0..1 can't be solved: 8 / 0 = undefined"
+    );
+
+    let input = parse("18446744073709551615\n18446744073709551615\n+");
+    assert_eq!(part1(&input), "36893488147419103230");
+
+    let input = parse("18446744073709551615\n18446744073709551615\n*");
+    assert!(part1(&input).ends_with("= undefined"));
 }
@@ -1,5 +1,4 @@
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
 
 pub fn parse(input: &str) -> Vec<Vec<char>> {
     input
@@ -26,41 +25,122 @@ fn next_cols(ch: char, col: usize, max_col: usize) -> Vec<usize> {
     }
 }
 
+// how many timelines pass through every cell. the counts double at each splitter, so past
+// about 128 splitter rows even u128 runs out: counts then stop at u128::MAX and the first
+// row that overflowed is kept, which leaves the beams and split count intact
+struct Simulation {
+    counts: Vec<Vec<u128>>,
+    splits: usize,
+    overflow: Option<usize>,
+}
+
+fn simulate(grid: &[Vec<char>]) -> Simulation {
+    let width = grid[0].len();
+    let mut counts = vec![vec![0u128; width]; grid.len()];
+    counts[0][find_start(grid)] = 1;
+    let mut splits = 0;
+    let mut overflow = None;
+
+    for row in 0..grid.len() - 1 {
+        for col in 0..width {
+            let n = counts[row][col];
+            if n == 0 {
+                continue;
+            }
+
+            let below = grid[row + 1][col];
+            splits += (below == '^') as usize;
+            next_cols(below, col, width).into_iter().for_each(|c| {
+                let next = &mut counts[row + 1][c];
+                *next = next.checked_add(n).unwrap_or_else(|| {
+                    overflow.get_or_insert(row + 1);
+                    u128::MAX
+                });
+            });
+        }
+    }
+
+    Simulation {
+        counts,
+        splits,
+        overflow,
+    }
+}
+
+impl Simulation {
+    // the total, or the row where counting overflowed
+    fn timelines(&self) -> Result<u128, usize> {
+        let last = self.counts.len() - 1;
+        match self.overflow {
+            Some(row) => Err(row),
+            None => self.counts[last]
+                .iter()
+                .try_fold(0u128, |total, &n| total.checked_add(n))
+                .ok_or(last),
+        }
+    }
+
+    fn render(&self, grid: &[Vec<char>]) -> String {
+        grid.iter()
+            .zip(&self.counts)
+            .map(|(row, counts)| {
+                row.iter()
+                    .zip(counts)
+                    .map(|(&ch, &n)| if ch == '.' && n > 0 { '|' } else { ch })
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
+
+    // counts span dozens of orders of magnitude on tall manifolds, so the shade follows the
+    // bit length of each count rather than the count itself
+    fn heatmap_pgm(&self) -> String {
+        let bits = |n: u128| 128 - n.leading_zeros();
+        let max = self
+            .counts
+            .iter()
+            .flatten()
+            .map(|&n| bits(n))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        format!(
+            "P2\n{} {}\n255\n{}",
+            self.counts[0].len(),
+            self.counts.len(),
+            self.counts
+                .iter()
+                .map(|row| row.iter().map(|&n| bits(n) * 255 / max).join(" "))
+                .join("\n")
+        )
+    }
+}
+
 pub fn part1(grid: &[Vec<char>]) -> String {
-    let start = find_start(grid);
-
-    (0..grid.len() - 1)
-        .fold((HashSet::from([start]), 0), |(beams, splits), row| {
-            let new_splits = beams.iter().filter(|&&c| grid[row + 1][c] == '^').count();
-            let next = beams
-                .into_iter()
-                .flat_map(|c| next_cols(grid[row + 1][c], c, grid[0].len()))
-                .collect();
-
-            (next, splits + new_splits)
-        })
-        .1
-        .to_string()
+    simulate(grid).splits.to_string()
 }
 
 pub fn part2(grid: &[Vec<char>]) -> String {
-    let start = find_start(grid);
-
-    (0..grid.len() - 1)
-        .fold(HashMap::from([(start, 1u64)]), |counts, row| {
-            counts
-                .into_iter()
-                .flat_map(|(c, n)| {
-                    next_cols(grid[row + 1][c], c, grid[0].len())
-                        .into_iter()
-                        .map(move |c| (c, n))
-                })
-                .into_grouping_map()
-                .sum()
-        })
-        .values()
-        .sum::<u64>()
-        .to_string()
+    match simulate(grid).timelines() {
+        Ok(timelines) => timelines.to_string(),
+        Err(row) => format!("more timelines than fit in u128 by row {row}"),
+    }
+}
+
+pub fn debug(grid: &[Vec<char>], args: &[String]) -> String {
+    let simulation = simulate(grid);
+
+    match args[0].as_str() {
+        "beams" => simulation.render(grid),
+        "counts" => simulation
+            .counts
+            .iter()
+            .map(|row| row.iter().join(","))
+            .join("\n"),
+        "heatmap" => simulation.heatmap_pgm(),
+        mode => format!("unknown mode {mode}, expected beams, counts or heatmap"),
+    }
 }
 
 #[test]
@@ -85,4 +165,22 @@ fn test_day07() {
     ));
     assert_eq!(part1(&input), "21");
     assert_eq!(part2(&input), "40");
+
+    let simulation = simulate(&input);
+    let beams = simulation.render(&input);
+    assert_eq!(beams.lines().nth(3), Some("......|.|......"));
+    assert_eq!(simulation.counts[4][7], 2);
+    assert_eq!(simulation.counts[15].iter().max(), Some(&11));
+
+    // a splitter on every other row of every column doubles the timelines each time
+    let tall = |splitters: usize| {
+        let mut rows = std::iter::once("S".to_string() + &".".repeat(200))
+            .chain((0..splitters).flat_map(|_| [".".repeat(201), "^".repeat(201)]));
+        simulate(&parse(&rows.join("\n")))
+    };
+    assert!(tall(100).timelines().unwrap() > u64::MAX as u128);
+
+    let overflowing = tall(140);
+    assert!(overflowing.timelines().is_err());
+    assert!(overflowing.splits > tall(100).splits);
 }
@@ -1,5 +1,7 @@
 use itertools::Itertools;
-use std::collections::HashSet;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 type Point = (i64, i64, i64);
 
@@ -18,42 +20,209 @@ fn square_distance(a: &Point, b: &Point) -> i64 {
     (a.0 - b.0).pow(2) + (a.1 - b.1).pow(2) + (a.2 - b.2).pow(2)
 }
 
-fn sorted_pairs(points: &[Point]) -> Vec<(i64, usize, usize)> {
-    (0..points.len())
-        .tuple_combinations()
-        .map(|(i, j)| (square_distance(&points[i], &points[j]), i, j))
-        .sorted_unstable()
-        .collect()
+fn axis(p: &Point, axis: usize) -> i64 {
+    [p.0, p.1, p.2][axis]
 }
 
-fn join(mut comps: Vec<HashSet<usize>>, i: usize, j: usize) -> Vec<HashSet<usize>> {
-    let (ci, cj) = (
-        comps.iter().position(|c| c.contains(&i)).unwrap(),
-        comps.iter().position(|c| c.contains(&j)).unwrap(),
-    );
+// implicit k-d tree: the median of every slice is its node, split on x, y, z by depth
+struct KdTree<'a> {
+    points: &'a [Point],
+    nodes: Vec<usize>,
+}
+
+impl<'a> KdTree<'a> {
+    fn new(points: &'a [Point]) -> Self {
+        fn build(points: &[Point], nodes: &mut [usize], depth: usize) {
+            if nodes.len() <= 1 {
+                return;
+            }
+            let mid = nodes.len() / 2;
+            nodes.select_nth_unstable_by_key(mid, |&i| axis(&points[i], depth % 3));
+
+            let (left, right) = nodes.split_at_mut(mid);
+            build(points, left, depth + 1);
+            build(points, &mut right[1..], depth + 1);
+        }
 
-    if ci != cj {
-        let merged = comps[ci].union(&comps[cj]).copied().collect();
-        comps.retain(|c| !c.contains(&i) && !c.contains(&j));
-        comps.push(merged);
+        let mut nodes = (0..points.len()).collect_vec();
+        build(points, &mut nodes, 0);
+        KdTree { points, nodes }
     }
 
-    comps
+    // the k closest other points to `target`, ordered by distance then index so ties come
+    // out exactly like sorting every pair would
+    fn nearest(&self, target: usize, k: usize) -> Vec<(i64, usize)> {
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        self.search(&self.nodes, 0, target, k, &mut best);
+        best.into_sorted_vec()
+    }
+
+    fn search(
+        &self,
+        nodes: &[usize],
+        depth: usize,
+        target: usize,
+        k: usize,
+        best: &mut BinaryHeap<(i64, usize)>,
+    ) {
+        if nodes.is_empty() {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let (node, p) = (nodes[mid], &self.points[target]);
+
+        if node != target {
+            best.push((square_distance(p, &self.points[node]), node));
+            if best.len() > k {
+                best.pop();
+            }
+        }
+
+        let diff = axis(p, depth % 3) - axis(&self.points[node], depth % 3);
+        let (near, far) = match diff < 0 {
+            true => (&nodes[..mid], &nodes[mid + 1..]),
+            false => (&nodes[mid + 1..], &nodes[..mid]),
+        };
+
+        self.search(near, depth + 1, target, k, best);
+        if best.len() < k || diff * diff <= best.peek().unwrap().0 {
+            self.search(far, depth + 1, target, k, best);
+        }
+    }
 }
 
-fn connect(points: &[Point], n: usize) -> Vec<HashSet<usize>> {
-    let init = (0..points.len()).map(|i| HashSet::from([i])).collect();
+// the neighbors of one point found so far, fetched again with twice as many once used up
+struct Neighbors {
+    found: Vec<(i64, usize)>,
+    next: usize,
+    k: usize,
+}
 
-    sorted_pairs(points)
-        .into_iter()
-        .take(n)
-        .fold(init, |comps, (_, i, j)| join(comps, i, j))
+// every pair of points, closest first, generated lazily by merging each point's nearest
+// neighbor list, a pair only comes from the lower of its two indices
+struct NearestPairs<'a> {
+    tree: KdTree<'a>,
+    neighbors: Vec<Neighbors>,
+    queue: BinaryHeap<Reverse<(i64, usize, usize)>>,
+}
+
+impl<'a> NearestPairs<'a> {
+    fn new(points: &'a [Point]) -> Self {
+        let k = 4;
+        let tree = KdTree::new(points);
+        let neighbors = (0..points.len())
+            .into_par_iter()
+            .map(|i| Neighbors {
+                found: tree.nearest(i, k),
+                next: 0,
+                k,
+            })
+            .collect();
+
+        let mut pairs = NearestPairs {
+            tree,
+            neighbors,
+            queue: BinaryHeap::new(),
+        };
+        (0..points.len()).for_each(|i| pairs.advance(i));
+        pairs
+    }
+
+    // queues the next neighbor of `i` with a higher index
+    fn advance(&mut self, i: usize) {
+        let neighbors = &mut self.neighbors[i];
+        loop {
+            if neighbors.next == neighbors.found.len() {
+                if neighbors.found.len() < neighbors.k {
+                    return;
+                }
+                neighbors.k *= 2;
+                neighbors.found = self.tree.nearest(i, neighbors.k);
+            }
+
+            let (d, j) = neighbors.found[neighbors.next];
+            neighbors.next += 1;
+            if j > i {
+                self.queue.push(Reverse((d, i, j)));
+                return;
+            }
+        }
+    }
+}
+
+impl Iterator for NearestPairs<'_> {
+    type Item = (i64, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(pair) = self.queue.pop()?;
+        self.advance(pair.1);
+        Some(pair)
+    }
+}
+
+// union-find over the junction boxes, tracking how many circuits are left
+struct Circuits {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+}
+
+impl Circuits {
+    fn new(n: usize) -> Self {
+        Circuits {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            count: n,
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut i = i;
+        while self.parent[i] != root {
+            i = std::mem::replace(&mut self.parent[i], root);
+        }
+        root
+    }
+
+    fn join(&mut self, i: usize, j: usize) {
+        let (mut a, mut b) = (self.find(i), self.find(j));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.count -= 1;
+    }
+
+    fn sizes(&self) -> Vec<usize> {
+        (0..self.parent.len())
+            .filter(|&i| self.parent[i] == i)
+            .map(|i| self.size[i])
+            .collect()
+    }
+}
+
+fn connect(points: &[Point], n: usize) -> Circuits {
+    NearestPairs::new(points).take(n).fold(
+        Circuits::new(points.len()),
+        |mut circuits, (_, i, j)| {
+            circuits.join(i, j);
+            circuits
+        },
+    )
 }
 
 pub fn part1(points: &[Point]) -> String {
     connect(points, 1000)
-        .iter()
-        .map(|c| c.len())
+        .sizes()
+        .into_iter()
         .sorted_unstable()
         .rev()
         .take(3)
@@ -62,17 +231,13 @@ pub fn part1(points: &[Point]) -> String {
 }
 
 pub fn part2(points: &[Point]) -> String {
-    let init = (0..points.len())
-        .map(|i| HashSet::from([i]))
-        .collect::<Vec<_>>();
+    let mut circuits = Circuits::new(points.len());
 
-    sorted_pairs(points)
-        .into_iter()
-        .scan(init, |comps, (_, i, j)| {
-            *comps = join(comps.clone(), i, j);
-            Some((comps.len() == 1, i, j))
+    NearestPairs::new(points)
+        .find(|&(_, i, j)| {
+            circuits.join(i, j);
+            circuits.count == 1
         })
-        .find(|(done, _, _)| *done)
         .map(|(_, i, j)| points[i].0 * points[j].0)
         .unwrap()
         .to_string()
@@ -104,4 +269,13 @@ fn test_day08() {
     );
     assert_eq!(part1(&input), "20"); // this differs from the actual test given, since it was only for 10 connections
     assert_eq!(part2(&input), "25272");
+
+    let sizes = connect(&input, 10).sizes();
+    assert_eq!(sizes.iter().sorted().rev().take(3).product::<usize>(), 40);
+
+    let closest = NearestPairs::new(&input)
+        .take(4)
+        .map(|(_, i, j)| (i, j))
+        .collect_vec();
+    assert_eq!(closest, [(0, 19), (0, 7), (2, 13), (7, 19)]);
 }
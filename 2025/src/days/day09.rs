@@ -1,8 +1,9 @@
-use itertools::{iproduct, Itertools};
-use std::collections::HashMap;
+mod polygon;
 
-type Point = (i64, i64);
-type PrefixSum = (Vec<Vec<i64>>, HashMap<i64, usize>, HashMap<i64, usize>);
+use itertools::Itertools;
+#[cfg(test)]
+use polygon::PolygonError;
+use polygon::{tiles, Point, Polygon};
 
 pub fn parse(input: &str) -> Vec<Point> {
     input
@@ -15,104 +16,46 @@ pub fn parse(input: &str) -> Vec<Point> {
         .collect()
 }
 
-fn area((x1, y1): &Point, (x2, y2): &Point) -> i64 {
-    ((x1 - x2).abs() + 1) * ((y1 - y2).abs() + 1)
-}
-
 pub fn part1(points: &[Point]) -> String {
     points
         .iter()
         .tuple_combinations()
-        .map(|(a, b)| area(a, b))
+        .map(|(&a, &b)| tiles(a, b))
         .max()
         .unwrap_or(0)
         .to_string()
 }
 
-fn compressed_coords(points: &[Point]) -> (Vec<i64>, Vec<i64>) {
-    let unique = |f: fn(&Point) -> i64| points.iter().map(f).sorted_unstable().dedup().collect();
-    (unique(|p| p.0), unique(|p| p.1))
-}
-
-fn vertical_edges(points: &[Point]) -> HashMap<i64, Vec<(i64, i64)>> {
-    points
-        .iter()
-        .circular_tuple_windows()
-        .filter(|((x1, _), (x2, _))| x1 == x2)
-        .map(|((x, y1), (_, y2))| (*x, (*y1.min(y2), *y1.max(y2))))
-        .into_group_map()
-}
-
-fn inside_polygon(x: i64, y: i64, edges: &HashMap<i64, Vec<(i64, i64)>>) -> bool {
-    edges
-        .iter()
-        .filter(|(&ex, _)| ex < x)
-        .flat_map(|(_, segs)| segs)
-        .filter(|(y1, y2)| y > *y1 && y <= *y2)
-        .count()
-        % 2
-        == 1
-}
-
-fn inside_grid(points: &[Point], xs: &[i64], ys: &[i64]) -> Vec<Vec<bool>> {
-    let edges = vertical_edges(points);
-    let mid = |s: &[i64], i| (s[i] + s[i + 1]) / 2;
-
-    (0..xs.len() - 1)
-        .map(|i| {
-            (0..ys.len() - 1)
-                .map(|j| inside_polygon(mid(xs, i), mid(ys, j), &edges))
-                .collect()
-        })
-        .collect()
+fn polygon(points: &[Point]) -> Polygon {
+    Polygon::new(points.to_vec()).unwrap_or_else(|e| panic!("Tiles don't form a loop: {e}"))
 }
 
-fn index_map(slice: &[i64]) -> HashMap<i64, usize> {
-    slice
-        .iter()
-        .copied()
-        .enumerate()
-        .map(|(i, v)| (v, i))
-        .collect()
+pub fn part2(points: &[Point]) -> String {
+    polygon(points)
+        .largest_rect()
+        .map_or(0, |(_, _, area)| area)
+        .to_string()
 }
 
-fn prefix_sum(inside: &[Vec<bool>], xs: &[i64], ys: &[i64]) -> PrefixSum {
-    let (nx, ny) = (inside.len(), inside.first().map_or(0, Vec::len));
-
-    // i hate mutability but it's the cleanest way to do this
-    let mut prefix = vec![vec![0i64; ny + 1]; nx + 1];
-    for (i, j) in iproduct!(0..nx, 0..ny) {
-        let val = i64::from(inside[i][j]);
-        prefix[i + 1][j + 1] = val + prefix[i][j + 1] + prefix[i + 1][j] - prefix[i][j];
+pub fn debug(points: &[Point], args: &[String]) -> String {
+    match args[0].as_str() {
+        "check" => match Polygon::new(points.to_vec()) {
+            Ok(polygon) => format!("valid loop covering {} tiles", polygon.area()),
+            Err(e) => format!("invalid loop: {e}"),
+        },
+        "contains" if args.len() < 3 => "usage: contains <x> <y>".to_string(),
+        "contains" => {
+            let coord = |i: usize| args[i].parse().expect("Coordinates must be numbers");
+            let p = (coord(1), coord(2));
+            format!("{p:?} inside: {}", polygon(points).contains(p))
+        }
+        "svg" => {
+            let polygon = polygon(points);
+            let best = polygon.largest_rect().map(|(a, b, _)| (a, b));
+            polygon.to_svg(best)
+        }
+        mode => format!("unknown mode {mode}, expected check, contains or svg"),
     }
-
-    (prefix, index_map(xs), index_map(ys))
-}
-
-fn valid_rect((prefix, xi, yi): &PrefixSum, a: &Point, b: &Point) -> bool {
-    let (i1, i2, j1, j2) = (
-        xi[&a.0.min(b.0)],
-        xi[&a.0.max(b.0)],
-        yi[&a.1.min(b.1)],
-        yi[&a.1.max(b.1)],
-    );
-
-    prefix[i2][j2] - prefix[i1][j2] - prefix[i2][j1] + prefix[i1][j1]
-        == ((i2 - i1) * (j2 - j1)) as i64
-}
-
-pub fn part2(points: &[Point]) -> String {
-    let (xs, ys) = compressed_coords(points);
-    let prefix = prefix_sum(&inside_grid(points, &xs, &ys), &xs, &ys);
-
-    points
-        .iter()
-        .tuple_combinations()
-        .filter(|(a, b)| valid_rect(&prefix, a, b))
-        .map(|(a, b)| area(a, b))
-        .max()
-        .unwrap_or(0)
-        .to_string()
 }
 
 #[test]
@@ -120,4 +63,43 @@ fn test_day09() {
     let input = parse("7,1\n11,1\n11,7\n9,7\n9,5\n2,5\n2,3\n7,3");
     assert_eq!(part1(&input), "50");
     assert_eq!(part2(&input), "24");
+
+    let polygon = Polygon::new(input.clone()).unwrap();
+    assert_eq!(polygon.area(), 46);
+    assert!(polygon.contains((8, 4)) && polygon.contains((2, 3)) && !polygon.contains((3, 2)));
+    assert_eq!(polygon.largest_rect(), Some(((9, 5), (2, 3), 24)));
+    assert!(polygon.to_svg(None).starts_with("<svg"));
+
+    let square = Polygon::new(parse("0,0\n1,0\n1,1\n0,1")).unwrap();
+    assert!(square.contains_rect((0, 0), (1, 1)));
+    assert_eq!(square.largest_rect().map(|(_, _, area)| area), Some(4));
+
+    let thin = Polygon::new(parse("0,0\n5,0\n5,1\n1,1\n1,5\n0,5")).unwrap();
+    assert_eq!(thin.largest_rect().map(|(_, _, area)| area), Some(12));
+
+    // the gap across the top of the U is outside even though it joins two vertices
+    let u = Polygon::new(parse("0,0\n10,0\n10,10\n8,10\n8,2\n2,2\n2,10\n0,10")).unwrap();
+    assert!(!u.contains((5, 10)));
+    assert!(!u.contains_rect((2, 10), (8, 10)));
+    assert!(u.contains_rect((0, 0), (10, 0)) && u.contains_rect((8, 2), (8, 10)));
+    assert_eq!(u.largest_rect().map(|(_, _, area)| area), Some(33));
+
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect_vec();
+    assert_eq!(
+        debug(&input, &args(&["contains", "1"])),
+        "usage: contains <x> <y>"
+    );
+
+    let crossing = parse("0,0\n4,0\n4,4\n2,4\n2,-2\n0,-2");
+    assert_eq!(
+        Polygon::new(crossing).err(),
+        Some(PolygonError::SelfIntersection(
+            ((0, 0), (4, 0)),
+            ((2, 4), (2, -2))
+        ))
+    );
+    assert_eq!(
+        Polygon::new(parse("0,0\n4,0\n4,4\n1,3")).err(),
+        Some(PolygonError::DiagonalEdge((4, 4), (1, 3)))
+    );
 }
@@ -0,0 +1,260 @@
+use itertools::{iproduct, Itertools};
+use std::collections::HashMap;
+use std::fmt;
+
+pub type Point = (i64, i64);
+
+#[derive(Debug, PartialEq)]
+pub enum PolygonError {
+    TooFewVertices(usize),
+    DiagonalEdge(Point, Point),
+    RepeatedVertex(Point),
+    SelfIntersection((Point, Point), (Point, Point)),
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolygonError::TooFewVertices(n) => write!(f, "a loop needs 4 vertices, got {n}"),
+            PolygonError::DiagonalEdge(a, b) => write!(f, "edge {a:?} -> {b:?} isn't axis aligned"),
+            PolygonError::RepeatedVertex(p) => write!(f, "vertex {p:?} repeats"),
+            PolygonError::SelfIntersection((a, b), (c, d)) => {
+                write!(f, "edges {a:?} -> {b:?} and {c:?} -> {d:?} cross")
+            }
+        }
+    }
+}
+
+// a closed loop of axis aligned edges, every vertex joined to the next and the last one back
+// to the first, with the inside worked out once on the grid compressed to the vertex coords
+pub struct Polygon {
+    pub vertices: Vec<Point>,
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    inside: Vec<Vec<bool>>,
+    prefix: Vec<Vec<i64>>,
+    x_index: HashMap<i64, usize>,
+    y_index: HashMap<i64, usize>,
+}
+
+fn bounds((a, b): (Point, Point)) -> (i64, i64, i64, i64) {
+    (a.0.min(b.0), a.0.max(b.0), a.1.min(b.1), a.1.max(b.1))
+}
+
+fn touches(e: (Point, Point), f: (Point, Point)) -> bool {
+    let ((ex1, ex2, ey1, ey2), (fx1, fx2, fy1, fy2)) = (bounds(e), bounds(f));
+    ex1.max(fx1) <= ex2.min(fx2) && ey1.max(fy1) <= ey2.min(fy2)
+}
+
+fn validate(vertices: &[Point]) -> Result<(), PolygonError> {
+    let n = vertices.len();
+    if n < 4 {
+        return Err(PolygonError::TooFewVertices(n));
+    }
+    if let Some(&p) = vertices.iter().duplicates().next() {
+        return Err(PolygonError::RepeatedVertex(p));
+    }
+
+    let edges = vertices
+        .iter()
+        .copied()
+        .circular_tuple_windows()
+        .collect_vec();
+    if let Some(&(a, b)) = edges.iter().find(|(a, b)| a.0 != b.0 && a.1 != b.1) {
+        return Err(PolygonError::DiagonalEdge(a, b));
+    }
+
+    // neighboring edges share a vertex, so they only clash when one doubles back over the other
+    let doubles_back = |(a, b): (Point, Point), (_, c): (Point, Point)| {
+        let (d1, d2) = ((b.0 - a.0, b.1 - a.1), (c.0 - b.0, c.1 - b.1));
+        d1.0 * d2.0 + d1.1 * d2.1 < 0
+    };
+
+    for (i, j) in (0..n).tuple_combinations() {
+        let clash = match (j - i, (i + n - j) % n) {
+            (1, _) => doubles_back(edges[i], edges[j]),
+            (_, 1) => doubles_back(edges[j], edges[i]),
+            _ => touches(edges[i], edges[j]),
+        };
+        if clash {
+            return Err(PolygonError::SelfIntersection(edges[i], edges[j]));
+        }
+    }
+    Ok(())
+}
+
+fn index_map(slice: &[i64]) -> HashMap<i64, usize> {
+    slice.iter().enumerate().map(|(i, &v)| (v, i)).collect()
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Point>) -> Result<Self, PolygonError> {
+        validate(&vertices)?;
+
+        let unique = |f: fn(&Point) -> i64| {
+            vertices
+                .iter()
+                .map(f)
+                .sorted_unstable()
+                .dedup()
+                .collect_vec()
+        };
+        let (xs, ys) = (unique(|p| p.0), unique(|p| p.1));
+
+        // twice the middle of a cell, halving would round onto an edge for cells one wide
+        let mid = |s: &[i64], i: usize| s[i] + s[i + 1];
+        let inside = (0..xs.len() - 1)
+            .map(|i| {
+                (0..ys.len() - 1)
+                    .map(|j| crosses_left(&vertices, mid(&xs, i), mid(&ys, j), 2))
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        let (nx, ny) = (xs.len() - 1, ys.len() - 1);
+        let mut prefix = vec![vec![0i64; ny + 1]; nx + 1];
+        for (i, j) in iproduct!(0..nx, 0..ny) {
+            let val = i64::from(inside[i][j]);
+            prefix[i + 1][j + 1] = val + prefix[i][j + 1] + prefix[i + 1][j] - prefix[i][j];
+        }
+
+        Ok(Polygon {
+            x_index: index_map(&xs),
+            y_index: index_map(&ys),
+            vertices,
+            xs,
+            ys,
+            inside,
+            prefix,
+        })
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.vertices.iter().copied().circular_tuple_windows()
+    }
+
+    // tiles covered, edges included: by pick's theorem that's the shoelace area plus half
+    // the boundary plus one
+    pub fn area(&self) -> i64 {
+        let (twice_area, boundary) = self.edges().fold((0, 0), |(area, boundary), (a, b)| {
+            (
+                area + a.0 * b.1 - b.0 * a.1,
+                boundary + (a.0 - b.0).abs() + (a.1 - b.1).abs(),
+            )
+        });
+        (twice_area.abs() + boundary) / 2 + 1
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        self.edges().any(|e| touches(e, (p, p))) || crosses_left(&self.vertices, p.0, p.1, 1)
+    }
+
+    // whether the rectangle with these two corners stays on the inside, corners being
+    // vertex coordinates so the rectangle lines up with the compressed grid
+    pub fn contains_rect(&self, a: Point, b: Point) -> bool {
+        let (x1, x2, y1, y2) = bounds((a, b));
+        let (Some(&i1), Some(&i2), Some(&j1), Some(&j2)) = (
+            self.x_index.get(&x1),
+            self.x_index.get(&x2),
+            self.y_index.get(&y1),
+            self.y_index.get(&y2),
+        ) else {
+            return false;
+        };
+
+        // a flat rectangle covers no cells, so each piece of it between two compressed coords
+        // has to border an inside cell on one side or the other
+        let cell = |i: Option<usize>, j: Option<usize>| {
+            let (i, j) = (i.unwrap_or(usize::MAX), j.unwrap_or(usize::MAX));
+            self.inside.get(i).and_then(|col| col.get(j)) == Some(&true)
+        };
+        match (i1 == i2, j1 == j2) {
+            (true, true) => self.contains(a),
+            (true, false) => {
+                (j1..j2).all(|j| cell(i1.checked_sub(1), Some(j)) || cell(Some(i1), Some(j)))
+            }
+            (false, true) => {
+                (i1..i2).all(|i| cell(Some(i), j1.checked_sub(1)) || cell(Some(i), Some(j1)))
+            }
+            (false, false) => {
+                let prefix = &self.prefix;
+                prefix[i2][j2] - prefix[i1][j2] - prefix[i2][j1] + prefix[i1][j1]
+                    == ((i2 - i1) * (j2 - j1)) as i64
+            }
+        }
+    }
+
+    // the biggest inside rectangle, counted in tiles, with opposite corners on two vertices
+    pub fn largest_rect(&self) -> Option<(Point, Point, i64)> {
+        self.vertices
+            .iter()
+            .tuple_combinations()
+            .filter(|&(&a, &b)| self.contains_rect(a, b))
+            .map(|(&a, &b)| (a, b, tiles(a, b)))
+            .max_by_key(|&(_, _, area)| area)
+    }
+
+    // the compressed grid, one square per cell with the inside filled in, the loop drawn over
+    // it and optionally a rectangle highlighted
+    pub fn to_svg(&self, rect: Option<(Point, Point)>) -> String {
+        let scale = 10;
+        let (w, h) = ((self.xs.len() - 1) * scale, (self.ys.len() - 1) * scale);
+        let at = |p: Point| (self.x_index[&p.0] * scale, self.y_index[&p.1] * scale);
+
+        let cells = iproduct!(0..self.inside.len(), 0..self.ys.len() - 1)
+            .filter(|&(i, j)| self.inside[i][j])
+            .map(|(i, j)| {
+                format!(
+                    r##"<rect x="{}" y="{}" width="{scale}" height="{scale}" fill="#9c6"/>"##,
+                    i * scale,
+                    j * scale
+                )
+            });
+        let outline = format!(
+            r##"<polygon points="{}" fill="none" stroke="#333" stroke-width="1"/>"##,
+            self.vertices
+                .iter()
+                .map(|&p| at(p))
+                .map(|(x, y)| format!("{x},{y}"))
+                .join(" ")
+        );
+        let highlight = rect.map(|(a, b)| {
+            let ((x1, y1), (x2, y2)) = (at(a), at(b));
+            format!(
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#c33" fill-opacity="0.5"/>"##,
+                x1.min(x2),
+                y1.min(y2),
+                x1.abs_diff(x2),
+                y1.abs_diff(y2)
+            )
+        });
+
+        std::iter::once(format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -1 {} {}">"#,
+            w + 2,
+            h + 2
+        ))
+        .chain(cells)
+        .chain(std::iter::once(outline))
+        .chain(highlight)
+        .chain(std::iter::once("</svg>".to_string()))
+        .join("\n")
+    }
+}
+
+pub fn tiles((x1, y1): Point, (x2, y2): Point) -> i64 {
+    ((x1 - x2).abs() + 1) * ((y1 - y2).abs() + 1)
+}
+
+// ray cast to the left, counting vertical edges crossed with the lower end excluded. the
+// point is in coordinates multiplied by `scale`, so a cell's center can be sampled exactly
+fn crosses_left(vertices: &[Point], x: i64, y: i64, scale: i64) -> bool {
+    vertices
+        .iter()
+        .circular_tuple_windows()
+        .filter(|((x1, _), (x2, _))| x1 == x2 && x1 * scale < x)
+        .filter(|((_, y1), (_, y2))| y > y1.min(y2) * scale && y <= y1.max(y2) * scale)
+        .count()
+        % 2
+        == 1
+}
@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 pub type Graph<'a> = HashMap<&'a str, Vec<&'a str>>;
 
@@ -11,72 +13,151 @@ pub fn parse(input: &str) -> Graph<'_> {
         .collect()
 }
 
+#[derive(Debug, PartialEq)]
+enum PathError<'a> {
+    Cycle(Vec<&'a str>),
+    // the node whose count went past u128
+    Overflow(&'a str),
+}
+
+impl fmt::Display for PathError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::Cycle(cycle) => write!(f, "cycle: {}", cycle.join(" -> ")),
+            PathError::Overflow(node) => write!(f, "more paths than fit in u128 at {node}"),
+        }
+    }
+}
+
+fn reachable<'a>(edges: &HashMap<&'a str, Vec<&'a str>>, from: &'a str) -> HashSet<&'a str> {
+    let mut seen = HashSet::from([from]);
+    let mut stack = vec![from];
+    while let Some(node) = stack.pop() {
+        for &next in edges.get(node).into_iter().flatten() {
+            if seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    seen
+}
+
+// depth first post order over the nodes that matter, a node seen again while it's still on
+// the path closes a cycle, and the path from there on (starting at its smallest name) is the error
+fn topological_order<'a>(
+    graph: &Graph<'a>,
+    node: &'a str,
+    relevant: &HashSet<&'a str>,
+    path: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+    order: &mut Vec<&'a str>,
+) -> Result<(), PathError<'a>> {
+    if let Some(i) = path.iter().position(|&n| n == node) {
+        let mut cycle = path[i..].to_vec();
+        let first = cycle.iter().position_min().unwrap();
+        cycle.rotate_left(first);
+        return Err(PathError::Cycle(cycle));
+    }
+    if !done.insert(node) {
+        return Ok(());
+    }
+
+    path.push(node);
+    for &next in graph.get(node).into_iter().flatten() {
+        if relevant.contains(next) {
+            topological_order(graph, next, relevant, path, done, order)?;
+        }
+    }
+    path.pop();
+    order.push(node);
+    Ok(())
+}
+
+// paths from start to end that visit every waypoint, tracked as a bitmask of the waypoints
+// seen so far. only nodes between start and end count, so a cycle elsewhere doesn't matter,
+// but one on the way is an error since it'd make infinitely many paths
 fn count_paths<'a>(
     graph: &Graph<'a>,
     start: &'a str,
-    end: &str,
-    memo: &mut HashMap<&'a str, usize>,
-) -> usize {
-    (start == end)
-        .then_some(1)
-        .or_else(|| memo.get(start).copied())
-        .unwrap_or_else(|| {
-            let count = graph
-                .get(start)
-                .map(|neighbors| {
-                    neighbors
-                        .iter()
-                        .map(|&n| count_paths(graph, n, end, memo))
-                        .sum()
-                })
-                .unwrap_or(0);
-            memo.insert(start, count);
-            count
-        })
+    end: &'a str,
+    waypoints: &[&str],
+) -> Result<u128, PathError<'a>> {
+    let reverse: HashMap<&str, Vec<&str>> = graph
+        .iter()
+        .flat_map(|(&from, outputs)| outputs.iter().map(move |&to| (to, from)))
+        .into_group_map();
+    let (forward, backward) = (reachable(graph, start), reachable(&reverse, end));
+    let relevant: HashSet<&str> = forward.intersection(&backward).copied().collect();
+    if relevant.is_empty() {
+        return Ok(0);
+    }
+
+    let mut order = vec![];
+    topological_order(
+        graph,
+        start,
+        &relevant,
+        &mut vec![],
+        &mut HashSet::new(),
+        &mut order,
+    )?;
+
+    let bit = |node: &str| {
+        waypoints
+            .iter()
+            .positions(|&w| w == node)
+            .fold(0, |mask, i| mask | 1 << i)
+    };
+    let full = (1 << waypoints.len()) - 1;
+
+    let mut ways: HashMap<&str, Vec<u128>> = HashMap::new();
+    ways.entry(start).or_insert(vec![0; full + 1])[bit(start)] = 1;
+
+    for &node in order.iter().rev() {
+        let Some(counts) = ways.get(node).cloned() else {
+            continue;
+        };
+        for &next in graph.get(node).into_iter().flatten() {
+            if !relevant.contains(next) {
+                continue;
+            }
+            let next_counts = ways.entry(next).or_insert(vec![0; full + 1]);
+            for (mask, &n) in counts.iter().enumerate() {
+                let count = &mut next_counts[mask | bit(next)];
+                *count = count.checked_add(n).ok_or(PathError::Overflow(next))?;
+            }
+        }
+    }
+
+    Ok(ways[end][full])
 }
 
-fn count_paths_through<'a>(
-    graph: &Graph<'a>,
-    node: &'a str,
-    end: &str,
-    state: (bool, bool),
-    required: (&str, &str),
-    memo: &mut HashMap<(&'a str, bool, bool), usize>,
-) -> usize {
-    let state = (state.0 || node == required.0, state.1 || node == required.1);
-
-    (node == end)
-        .then(|| usize::from(state.0 && state.1))
-        .or_else(|| memo.get(&(node, state.0, state.1)).copied())
-        .unwrap_or_else(|| {
-            let count = graph
-                .get(node)
-                .map(|neighbors| {
-                    neighbors
-                        .iter()
-                        .map(|&n| count_paths_through(graph, n, end, state, required, memo))
-                        .sum()
-                })
-                .unwrap_or(0);
-            memo.insert((node, state.0, state.1), count);
-            count
-        })
+fn paths(graph: &Graph, start: &str, end: &str, waypoints: &[&str]) -> String {
+    match count_paths(graph, start, end, waypoints) {
+        Ok(count) => count.to_string(),
+        Err(e) => panic!("Can't count paths from {start} to {end}: {e}"),
+    }
 }
 
 pub fn part1(graph: &Graph) -> String {
-    count_paths(graph, "you", "out", &mut HashMap::new()).to_string()
+    paths(graph, "you", "out", &[])
 }
 
 pub fn part2(graph: &Graph) -> String {
-    count_paths_through(
-        graph,
-        "svr",
-        "out",
-        (false, false),
-        ("dac", "fft"),
-        &mut HashMap::new(),
-    )
-    .to_string()
+    paths(graph, "svr", "out", &["dac", "fft"])
+}
+
+pub fn debug(graph: &Graph, args: &[String]) -> String {
+    match args {
+        [mode, start, end, waypoints @ ..] if mode == "paths" => {
+            let waypoints = waypoints.iter().map(String::as_str).collect_vec();
+            match count_paths(graph, start, end, &waypoints) {
+                Ok(count) => format!("{count} paths from {start} to {end} through {waypoints:?}"),
+                Err(e) => e.to_string(),
+            }
+        }
+        _ => "expected paths <start> <end> [waypoints...]".to_string(),
+    }
 }
 
 #[test]
@@ -86,4 +167,43 @@ fn test_day11() {
 
     assert_eq!(part1(&input), "5");
     assert_eq!(part2(&input2), "2");
+
+    assert_eq!(count_paths(&input2, "svr", "out", &[]), Ok(8));
+    assert_eq!(count_paths(&input2, "svr", "out", &["hub"]), Ok(4));
+    assert_eq!(
+        count_paths(&input2, "svr", "out", &["fft", "dac", "hhh"]),
+        Ok(1)
+    );
+    assert_eq!(count_paths(&input2, "svr", "out", &["fft", "tty"]), Ok(0));
+
+    // a chain of 100 diamonds has more paths than fit in a u64
+    let diamonds = (0..100)
+        .map(|i| format!("n{i}: a{i} b{i}\na{i}: n{0}\nb{i}: n{0}", i + 1))
+        .join("\n");
+    assert_eq!(
+        count_paths(&parse(&diamonds), "n0", "n100", &[]),
+        Ok(1 << 100)
+    );
+
+    let diamonds = (0..130)
+        .map(|i| format!("n{i}: a{i} b{i}\na{i}: n{0}\nb{i}: n{0}", i + 1))
+        .join("\n");
+    assert_eq!(
+        count_paths(&parse(&diamonds), "n0", "n130", &[]),
+        Err(PathError::Overflow("n128"))
+    );
+
+    let cyclic = parse("svr: aaa\naaa: bbb dead\nbbb: aaa out\ndead: dead");
+    assert_eq!(
+        count_paths(&cyclic, "svr", "out", &[]),
+        Err(PathError::Cycle(vec!["aaa", "bbb"]))
+    );
+    assert_eq!(
+        count_paths(&cyclic, "bbb", "dead", &[]),
+        Err(PathError::Cycle(vec!["aaa", "bbb"]))
+    );
+    assert_eq!(
+        count_paths(&cyclic, "svr", "bbb", &["dead"]),
+        Err(PathError::Cycle(vec!["aaa", "bbb"]))
+    );
 }
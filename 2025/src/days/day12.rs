@@ -1,3 +1,4 @@
+use itertools::Itertools;
 use rayon::prelude::*;
 use std::collections::HashSet;
 
@@ -78,95 +79,223 @@ pub fn parse(input: &str) -> Input {
     Input { shapes, regions }
 }
 
-fn can_place(
-    grid: &[Vec<bool>],
-    shape: &Shape,
-    (pr, pc): (usize, usize),
-    h: usize,
+#[derive(Clone)]
+struct Bitboard(Vec<u64>);
+
+impl Bitboard {
+    fn new(cells: usize) -> Self {
+        Bitboard(vec![0; cells.div_ceil(64)])
+    }
+
+    fn set(&mut self, cell: usize) {
+        self.0[cell / 64] |= 1 << (cell % 64);
+    }
+
+    fn get(&self, cell: usize) -> bool {
+        self.0[cell / 64] >> (cell % 64) & 1 == 1
+    }
+
+    fn overlaps(&self, other: &Bitboard) -> bool {
+        self.0.iter().zip(&other.0).any(|(a, b)| a & b != 0)
+    }
+
+    fn toggle(&mut self, other: &Bitboard) {
+        self.0.iter_mut().zip(&other.0).for_each(|(a, b)| *a ^= b);
+    }
+
+    fn union(&mut self, other: &Bitboard) {
+        self.0.iter_mut().zip(&other.0).for_each(|(a, b)| *a |= b);
+    }
+
+    fn count(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+}
+
+// algorithm x over bitboard rows: each shape is a primary column that has to be covered
+// once per copy, while cells are secondary columns that a slack budget allows to stay empty
+struct Packing {
     w: usize,
-) -> bool {
-    shape.iter().all(|&(dr, dc)| {
-        let (r, c) = (pr as i32 + dr, pc as i32 + dc);
-        r >= 0 && c >= 0 && (r as usize) < h && (c as usize) < w && !grid[r as usize][c as usize]
-    })
+    h: usize,
+    sizes: Vec<usize>,
+    rows: Vec<Vec<Bitboard>>,
+}
+
+impl Packing {
+    fn new(shapes: &[Vec<Shape>], w: usize, h: usize) -> Self {
+        let rows = shapes
+            .iter()
+            .map(|orientations| {
+                orientations
+                    .iter()
+                    .flat_map(|shape| {
+                        let (max_r, max_c) = shape.iter().fold((0, 0), |(mr, mc), &(r, c)| {
+                            (mr.max(r as usize), mc.max(c as usize))
+                        });
+                        (0..h.saturating_sub(max_r))
+                            .flat_map(move |r| (0..w.saturating_sub(max_c)).map(move |c| (r, c)))
+                            .map(move |(r, c)| {
+                                shape
+                                    .iter()
+                                    .fold(Bitboard::new(w * h), |mut bits, &(dr, dc)| {
+                                        bits.set((r + dr as usize) * w + c + dc as usize);
+                                        bits
+                                    })
+                            })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Packing {
+            w,
+            h,
+            sizes: shapes
+                .iter()
+                .map(|o| o.first().map_or(0, Vec::len))
+                .collect(),
+            rows,
+        }
+    }
+
+    // copies of the same shape use rows in increasing order, so no permutation is tried twice
+    fn search(
+        &self,
+        filled: &mut Bitboard,
+        counts: &mut [usize],
+        next_row: &mut [usize],
+        placed: &mut Vec<(usize, usize)>,
+    ) -> bool {
+        let needed: usize = counts.iter().zip(&self.sizes).map(|(c, s)| c * s).sum();
+        if needed == 0 {
+            return true;
+        }
+
+        let slack = self.w * self.h - filled.count() - needed;
+        let mut reachable = Bitboard::new(self.w * self.h);
+        let mut best: Option<(usize, Vec<usize>)> = None;
+
+        for shape in (0..counts.len()).filter(|&s| counts[s] > 0) {
+            let options = (next_row[shape]..self.rows[shape].len())
+                .filter(|&row| !self.rows[shape][row].overlaps(filled))
+                .collect_vec();
+
+            if options.len() < counts[shape] {
+                return false;
+            }
+            options
+                .iter()
+                .for_each(|&row| reachable.union(&self.rows[shape][row]));
+            if best.as_ref().is_none_or(|(_, o)| options.len() < o.len()) {
+                best = Some((shape, options));
+            }
+        }
+
+        // every free cell no remaining shape can reach has to be paid for out of the slack
+        reachable.union(filled);
+        if self.w * self.h - reachable.count() > slack {
+            return false;
+        }
+
+        let (shape, options) = best.unwrap();
+        let previous = next_row[shape];
+
+        let found = options.into_iter().any(|row| {
+            let bits = &self.rows[shape][row];
+            filled.toggle(bits);
+            counts[shape] -= 1;
+            next_row[shape] = row + 1;
+            placed.push((shape, row));
+
+            let found = self.search(filled, counts, next_row, placed);
+            if !found {
+                placed.pop();
+                counts[shape] += 1;
+                filled.toggle(bits);
+            }
+            found
+        });
+
+        next_row[shape] = previous;
+        found
+    }
 }
 
-fn place(grid: &mut [Vec<bool>], shape: &Shape, (pr, pc): (usize, usize), val: bool) {
-    shape
+// a packing as a grid of cells, with a letter per piece and '.' for empty cells
+type Layout = Vec<Vec<char>>;
+
+// when every shape fits in a 3x3 box and there are enough boxes, no search is needed
+fn pack_in_blocks(shapes: &[Vec<Shape>], w: usize, h: usize, counts: &[usize]) -> Option<Layout> {
+    let fits_block = shapes.iter().all(|o| {
+        o.first()
+            .is_some_and(|s| s.iter().all(|&(r, c)| r < 3 && c < 3))
+    });
+    if !fits_block || counts.iter().sum::<usize>() > (w / 3) * (h / 3) {
+        return None;
+    }
+
+    let mut layout = vec![vec!['.'; w]; h];
+    counts
         .iter()
-        .for_each(|&(dr, dc)| grid[(pr as i32 + dr) as usize][(pc as i32 + dc) as usize] = val);
+        .enumerate()
+        .flat_map(|(shape, &count)| std::iter::repeat_n(shape, count))
+        .enumerate()
+        .for_each(|(piece, shape)| {
+            let (br, bc) = (piece / (w / 3) * 3, piece % (w / 3) * 3);
+            shapes[shape][0]
+                .iter()
+                .for_each(|&(r, c)| layout[br + r as usize][bc + c as usize] = letter(piece));
+        });
+    Some(layout)
+}
+
+fn letter(piece: usize) -> char {
+    let letters = ('A'..='Z').chain('a'..='z').collect_vec();
+    letters[piece % letters.len()]
 }
 
-fn remaining_cells(shapes: &[Vec<Shape>], counts: &[usize]) -> usize {
-    shapes
+fn pack(shapes: &[Vec<Shape>], w: usize, h: usize, counts: &[usize]) -> Option<Layout> {
+    let area: usize = shapes
         .iter()
         .zip(counts)
         .map(|(o, &c)| o.first().map_or(0, |s| s.len() * c))
-        .sum()
-}
+        .sum();
+    if area > w * h {
+        return None;
+    }
+    if let Some(layout) = pack_in_blocks(shapes, w, h, counts) {
+        return Some(layout);
+    }
 
-fn solve(
-    grid: &mut Vec<Vec<bool>>,
-    shapes: &[Vec<Shape>],
-    counts: &mut [usize],
-    h: usize,
-    w: usize,
-    min_pos: usize,
-) -> bool {
-    let Some(idx) = counts.iter().position(|&c| c > 0) else {
-        return true;
-    };
-
-    let (sr, sc) = (min_pos / w, min_pos % w);
-
-    (sr..h).any(|r| {
-        ((r > sr).then_some(0).unwrap_or(sc)..w).any(|c| {
-            shapes[idx].iter().any(|shape| {
-                can_place(grid, shape, (r, c), h, w) && {
-                    place(grid, shape, (r, c), true);
-                    counts[idx] -= 1;
-
-                    let found = remaining_cells(shapes, counts)
-                        <= grid.iter().flatten().filter(|&&x| !x).count()
-                        && solve(
-                            grid,
-                            shapes,
-                            counts,
-                            h,
-                            w,
-                            (counts[idx] > 0).then_some(r * w + c).unwrap_or(0),
-                        );
-
-                    found.then_some(()).unwrap_or_else(|| {
-                        counts[idx] += 1;
-                        place(grid, shape, (r, c), false);
-                    });
-
-                    found
-                }
-            })
-        })
-    })
-}
+    let packing = Packing::new(shapes, w, h);
+    let mut placed = vec![];
+    let found = packing.search(
+        &mut Bitboard::new(w * h),
+        &mut counts.to_vec(),
+        &mut vec![0; shapes.len()],
+        &mut placed,
+    );
 
-fn can_fit(shapes: &[Vec<Shape>], w: usize, h: usize, counts: &[usize]) -> bool {
-    remaining_cells(shapes, counts) <= w * h
-        && (counts.iter().sum::<usize>() == 0
-            || solve(
-                &mut vec![vec![false; w]; h],
-                shapes,
-                &mut counts.to_vec(),
-                h,
-                w,
-                0,
-            ))
+    found.then(|| {
+        let mut layout = vec![vec!['.'; w]; h];
+        placed
+            .iter()
+            .enumerate()
+            .for_each(|(piece, &(shape, row))| {
+                let bits = &packing.rows[shape][row];
+                (0..w * h)
+                    .filter(|&cell| bits.get(cell))
+                    .for_each(|cell| layout[cell / w][cell % w] = letter(piece));
+            });
+        layout
+    })
 }
 
 pub fn part1(input: &Input) -> String {
     input
         .regions
         .par_iter()
-        .filter(|(w, h, c)| can_fit(&input.shapes, *w, *h, c))
+        .filter(|(w, h, c)| pack(&input.shapes, *w, *h, c).is_some())
         .count()
         .to_string()
 }
@@ -175,6 +304,26 @@ pub fn part2(_input: &Input) -> String {
     "0".to_string()
 }
 
+pub fn debug(input: &Input, args: &[String]) -> String {
+    match args[0].as_str() {
+        "pack" if args.len() < 2 => "usage: pack <region>".to_string(),
+        "pack" => {
+            let region = args[1].parse::<usize>().expect("Region must be a number");
+            let Some((w, h, counts)) = input.regions.get(region) else {
+                return format!("no region {region}, there are {}", input.regions.len());
+            };
+            match pack(&input.shapes, *w, *h, counts) {
+                Some(layout) => layout
+                    .iter()
+                    .map(|row| row.iter().collect::<String>())
+                    .join("\n"),
+                None => format!("{w}x{h} can't fit {counts:?}"),
+            }
+        }
+        mode => format!("unknown mode {mode}, expected pack"),
+    }
+}
+
 #[test]
 fn test_day12() {
     let input = parse(concat!(
@@ -191,4 +340,17 @@ fn test_day12() {
 
     assert_eq!(part1(&input), "2");
     assert_eq!(part2(&input), "0");
+
+    let (w, h, counts) = &input.regions[1];
+    let layout = pack(&input.shapes, *w, *h, counts).unwrap();
+    let filled = layout.iter().flatten().filter(|&&c| c != '.').count();
+    assert_eq!(filled, 7 + 7 + 2 * 7 + 2 * 7);
+    assert_eq!(layout.iter().flatten().filter(|&&c| c == 'F').count(), 7);
+
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect_vec();
+    assert_eq!(debug(&input, &args(&["pack"])), "usage: pack <region>");
+    assert_eq!(
+        debug(&input, &args(&["pack", "3"])),
+        "no region 3, there are 3"
+    );
 }
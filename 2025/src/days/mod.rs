@@ -1,4 +1,5 @@
 type DaySolution = (fn(&str) -> String, fn(&str) -> String);
+type DayDebug = fn(&str, &[String]) -> String;
 
 macro_rules! make_day {
     ($($day:ident),*) => {
@@ -16,3 +17,17 @@ macro_rules! make_day {
 }
 
 make_day!(day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12);
+
+macro_rules! make_debug {
+    ($($num:literal => $day:ident),*) => {
+        const DEBUG: &[(usize, DayDebug)] = &[
+            $(($num, |input, args| $day::debug(&$day::parse(input), args)),)*
+        ];
+
+        pub fn debug(day: usize) -> Option<DayDebug> {
+            DEBUG.iter().find(|(num, _)| *num == day).map(|&(_, debug)| debug)
+        }
+    }
+}
+
+make_debug!(1 => day01, 3 => day03, 4 => day04, 6 => day06, 7 => day07, 9 => day09, 11 => day11, 12 => day12);
@@ -6,7 +6,7 @@ mod days;
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: {} <day>", args[0]);
+        eprintln!("Usage: {} <day> [input] [debug args...]", args[0]);
         std::process::exit(1);
     }
 
@@ -15,11 +15,17 @@ fn main() {
         panic!("Day {day} not implemented");
     }
 
-    let input = match args.len() {
-        3 => fs::read_to_string(&args[2]).expect("Failed to read input"),
-        _ => fs::read_to_string(format!("input/day{day}.txt")).expect("Failed to read input"),
+    let input = match args.get(2) {
+        Some(path) => fs::read_to_string(path).expect("Failed to read input"),
+        None => fs::read_to_string(format!("input/day{day}.txt")).expect("Failed to read input"),
     };
 
+    if args.len() > 3 {
+        let debug = days::debug(day).unwrap_or_else(|| panic!("Day {day} has no debug tools"));
+        println!("{}", debug(&input, &args[3..]));
+        return;
+    }
+
     let (part1, part2) = days::SOLUTIONS[day - 1];
 
     println!("Day {day}:");